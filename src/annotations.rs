@@ -0,0 +1,852 @@
+//! The comments and form field tooltips of a pdf, read with lopdf and written back into a
+//! copy of the pdf once they are translated.
+//!
+//! Only the texts are replaced, everything else in the copy, such as the values of the form
+//! fields and the names scripts and submitted data refer to them by, is left as it is. When
+//! the metadata is translated too, its title, subject, keywords and bookmark titles are
+//! replaced as well.
+//!
+//! lopdf only decrypts pdfs encrypted with RC4, and only with the user password, so the
+//! annotations of pdfs encrypted with AES or opened with the owner password cannot be read.
+//! It also leaves the strings inside dictionaries encrypted, which is where the texts of
+//! comments and fields are, so those are decrypted here.
+
+use crate::cache;
+use crate::error::{self, Error};
+use crate::metadata::{Metadata, OutlineEntry};
+use crate::translator::{self, TranslationBackend};
+use lopdf::{Dictionary, Object, ObjectId, StringFormat};
+use std::collections::{BTreeSet, HashMap};
+use std::path::{Path, PathBuf};
+
+/// The kinds of annotations whose contents are not a comment: links have none, popups
+/// show the contents of their parent and widgets are the form fields themselves.
+const NOT_COMMENTS: &[&[u8]] = &[b"Link", b"Popup", b"Widget"];
+
+/// The key of the text of a comment annotation.
+const CONTENTS: &[u8] = b"Contents";
+/// The key of the tooltip of a form field, which viewers also show as its label.
+const TOOLTIP: &[u8] = b"TU";
+
+/// A text of a comment or a form field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnotationText {
+    /// The annotation or field the text belongs to
+    pub object: ObjectId,
+    /// Whether the text is the tooltip of a form field rather than a comment
+    pub tooltip: bool,
+    /// The page the annotation is on, starting at 1, which fields outside any page lack
+    pub page: Option<usize>,
+    pub text: String,
+}
+
+impl AnnotationText {
+    fn key(&self) -> &'static [u8] {
+        if self.tooltip {
+            TOOLTIP
+        } else {
+            CONTENTS
+        }
+    }
+}
+
+/// A pdf and the texts of its comments and form fields.
+#[derive(Debug)]
+pub struct Annotations {
+    document: lopdf::Document,
+    pub texts: Vec<AnnotationText>,
+}
+
+impl Annotations {
+    /// Reads the comments and form field tooltips of the pdf at `path`, decrypting it with
+    /// `password` if it is encrypted.
+    ///
+    /// A pdf that cannot be decrypted with `password`, because it is encrypted with AES or
+    /// the password is the owner password, fails with [`Error::Extract`].
+    pub fn read(path: &Path, password: Option<&str>) -> error::Result<Annotations> {
+        let extract_error = |message: String| Error::Extract {
+            file: path.to_path_buf(),
+            page: None,
+            message,
+        };
+        let mut document = lopdf::Document::load(path).map_err(|e| match e {
+            lopdf::Error::IO(e) => Error::io(path, e),
+            e => extract_error(format!("Could not read the annotations: {}", e)),
+        })?;
+
+        if document.is_encrypted() {
+            // a pdf that only restricts editing opens with an empty user password
+            decrypt(&mut document, password.unwrap_or("")).map_err(|e| match e {
+                    lopdf::Error::Decryption(
+                        lopdf::encryption::DecryptionError::IncorrectPassword,
+                    ) if password.is_none() => Error::Encrypted {
+                        file: path.to_path_buf(),
+                        password_given: false,
+                    },
+                    e => extract_error(format!(
+                        "Could not decrypt the annotations, which only works for pdfs encrypted with RC4 and with the user password: {}",
+                        e
+                    )),
+                })?;
+        }
+
+        let texts = find_texts(&document);
+        Ok(Annotations { document, texts })
+    }
+
+    /// Whether the pdf has no comments or tooltips to translate.
+    pub fn is_empty(&self) -> bool {
+        self.texts.is_empty()
+    }
+
+    /// Translates the comments and tooltips into `target`, reusing and filling `cache` like
+    /// the text of the document. The translations are in the order of [`Annotations::texts`].
+    pub async fn translate(
+        &self,
+        backend: &dyn TranslationBackend,
+        source: &str,
+        target: &str,
+        cache: &cache::SharedCache,
+    ) -> error::Result<Vec<String>> {
+        let mut translations = Vec::with_capacity(self.texts.len());
+        for text in &self.texts {
+            translations.push(
+                translator::translate_cached(backend, &text.text, source, target, cache).await?,
+            );
+        }
+        Ok(translations)
+    }
+
+    /// Writes a copy of the pdf to `path` with [`Annotations::texts`] replaced by
+    /// `translations`, in the same order, and, when `metadata` has the metadata read from
+    /// the pdf and its translation, with the document info and bookmarks translated.
+    ///
+    /// The copy is written without encryption, as the password that opened it is not
+    /// necessarily the owner password needed to encrypt it again.
+    pub fn save(
+        &self,
+        path: &Path,
+        translations: &[String],
+        metadata: Option<(&Metadata, &Metadata)>,
+    ) -> error::Result<()> {
+        let mut document = self.document.clone();
+        if let Some((original, translated)) = metadata {
+            set_metadata(&mut document, original, translated);
+        }
+        for (text, translation) in self.texts.iter().zip(translations) {
+            let Ok(dictionary) = document.get_dictionary_mut(text.object) else {
+                continue;
+            };
+            dictionary.set(text.key(), encode_text_string(translation));
+            if !text.tooltip {
+                // viewers prefer the rich text and the drawn appearance of a comment over
+                // its contents, without them they show the translation instead
+                dictionary.remove(b"RC");
+                if dictionary
+                    .get(b"Subtype")
+                    .is_ok_and(|subtype| subtype.as_name().is_ok_and(|name| name == b"FreeText"))
+                {
+                    dictionary.remove(b"AP");
+                }
+            }
+        }
+
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+            }
+        }
+        document.save(path).map_err(|e| Error::Io {
+            path: Some(PathBuf::from(path)),
+            source: std::io::Error::new(std::io::ErrorKind::Other, e.to_string()),
+        })?;
+        Ok(())
+    }
+}
+
+/// Replaces the title, subject and keywords in the document info of `document` and the
+/// titles of its bookmarks with those of `translated`.
+///
+/// The bookmarks are found by their titles in `original`, which was read with poppler
+/// rather than lopdf. The XMP metadata that some viewers show instead of the document info
+/// is left as it is.
+fn set_metadata(document: &mut lopdf::Document, original: &Metadata, translated: &Metadata) {
+    let info = [
+        (&b"Title"[..], &translated.title),
+        (b"Subject", &translated.subject),
+        (b"Keywords", &translated.keywords),
+    ];
+    if info.iter().any(|(_, value)| value.is_some()) {
+        let id = match document.trailer.get(b"Info").and_then(Object::as_reference) {
+            Ok(id) => id,
+            Err(_) => {
+                let id = document.add_object(Dictionary::new());
+                document.trailer.set("Info", id);
+                id
+            }
+        };
+        if let Ok(dictionary) = document.get_dictionary_mut(id) {
+            for (key, value) in info {
+                if let Some(value) = value {
+                    dictionary.set(key, encode_text_string(value));
+                }
+            }
+        }
+    }
+
+    let mut titles = HashMap::new();
+    outline_titles(&original.outline, &translated.outline, &mut titles);
+    let mut stack: Vec<ObjectId> = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"Outlines"))
+        .and_then(|outlines| document.dereference(outlines))
+        .and_then(|(_, outlines)| outlines.as_dict())
+        .and_then(|outlines| outlines.get(b"First"))
+        .and_then(Object::as_reference)
+        .into_iter()
+        .collect();
+    let mut visited = BTreeSet::new();
+    while let Some(object) = stack.pop() {
+        // a broken outline can refer back to a bookmark above, which would loop forever
+        if !visited.insert(object) {
+            continue;
+        }
+        let Ok(item) = document.get_dictionary_mut(object) else {
+            continue;
+        };
+        let title = item
+            .get(b"Title")
+            .and_then(Object::as_str)
+            .map(decode_text_string)
+            .unwrap_or_default();
+        if let Some(translation) = titles.get(title.trim()) {
+            item.set("Title", encode_text_string(translation));
+        }
+        for key in [&b"Next"[..], b"First"] {
+            if let Ok(next) = item.get(key).and_then(Object::as_reference) {
+                stack.push(next);
+            }
+        }
+    }
+}
+
+/// Maps the titles of the bookmarks in `original` to those at the same place in
+/// `translated`.
+fn outline_titles<'a>(
+    original: &'a [OutlineEntry],
+    translated: &'a [OutlineEntry],
+    titles: &mut HashMap<&'a str, &'a str>,
+) {
+    for (original, translated) in original.iter().zip(translated) {
+        titles.insert(&original.title, &translated.title);
+        outline_titles(&original.children, &translated.children, titles);
+    }
+}
+
+/// Decrypts `document` with `password`.
+///
+/// [`lopdf::Document::decrypt`] only decrypts the strings and streams that are objects of
+/// their own and the entries of the document information, so the strings inside the other
+/// dictionaries and arrays, such as the contents of an annotation, are decrypted first.
+fn decrypt(document: &mut lopdf::Document, password: &str) -> lopdf::Result<()> {
+    let key = lopdf::encryption::get_encryption_key(document, password, true)?;
+    let encrypt = document
+        .trailer
+        .get(b"Encrypt")
+        .and_then(Object::as_reference)
+        .ok();
+    let info = document
+        .trailer
+        .get(b"Info")
+        .and_then(Object::as_reference)
+        .ok();
+    for (&id, object) in document.objects.iter_mut() {
+        if Some(id) == encrypt {
+            continue;
+        }
+        let values: Box<dyn Iterator<Item = &mut Object>> = match object {
+            Object::Dictionary(dictionary) => Box::new(dictionary.iter_mut().map(|(_, v)| v)),
+            Object::Stream(stream) => Box::new(stream.dict.iter_mut().map(|(_, v)| v)),
+            Object::Array(items) => Box::new(items.iter_mut()),
+            _ => continue,
+        };
+        for value in values {
+            // the strings of the document information are decrypted by lopdf
+            if Some(id) == info && matches!(value, Object::String(..)) {
+                continue;
+            }
+            decrypt_strings(&key, id, value);
+        }
+    }
+    document.decrypt(password)
+}
+
+/// Decrypts the strings in `object`, which is, or is inside, the object `id`.
+fn decrypt_strings(key: &[u8], id: ObjectId, object: &mut Object) {
+    match object {
+        Object::String(..) => {
+            if let Ok(decrypted) = lopdf::encryption::decrypt_object(key, id, object) {
+                if let Object::String(bytes, _) = object {
+                    *bytes = decrypted;
+                }
+            }
+        }
+        Object::Array(items) => {
+            for item in items {
+                decrypt_strings(key, id, item);
+            }
+        }
+        Object::Dictionary(dictionary) => {
+            for (_, value) in dictionary.iter_mut() {
+                decrypt_strings(key, id, value);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Finds the contents of the comments on every page and the tooltips of the form fields,
+/// both of the widgets on the pages and of the fields that group them in the form.
+fn find_texts(document: &lopdf::Document) -> Vec<AnnotationText> {
+    let mut texts = Vec::new();
+    let mut seen: BTreeSet<(ObjectId, bool)> = BTreeSet::new();
+    let mut add = |object: ObjectId, dictionary: &Dictionary, tooltip: bool, page| {
+        let key = if tooltip { TOOLTIP } else { CONTENTS };
+        let Ok(text) = dictionary.get(key).and_then(Object::as_str) else {
+            return;
+        };
+        let text = decode_text_string(text);
+        if !text.trim().is_empty() && seen.insert((object, tooltip)) {
+            texts.push(AnnotationText {
+                object,
+                tooltip,
+                page,
+                text,
+            });
+        }
+    };
+
+    for (number, page) in document.get_pages() {
+        let annotations = document
+            .get_dictionary(page)
+            .and_then(|page| page.get(b"Annots"))
+            .and_then(|annotations| document.dereference(annotations))
+            .and_then(|(_, annotations)| annotations.as_array());
+        let Ok(annotations) = annotations else {
+            continue;
+        };
+        for object in annotations.iter().filter_map(|a| a.as_reference().ok()) {
+            let Ok(annotation) = document.get_dictionary(object) else {
+                continue;
+            };
+            let subtype = annotation
+                .get(b"Subtype")
+                .and_then(Object::as_name)
+                .unwrap_or_default();
+            if subtype == b"Widget" {
+                add(object, annotation, true, Some(number as usize));
+            } else if !NOT_COMMENTS.contains(&subtype) {
+                add(object, annotation, false, Some(number as usize));
+            }
+        }
+    }
+
+    // fields with several widgets keep the tooltip in the field above them
+    let fields = document
+        .catalog()
+        .and_then(|catalog| catalog.get(b"AcroForm"))
+        .and_then(|form| document.dereference(form))
+        .and_then(|(_, form)| form.as_dict())
+        .and_then(|form| form.get(b"Fields"))
+        .and_then(|fields| document.dereference(fields))
+        .and_then(|(_, fields)| fields.as_array());
+    let mut stack: Vec<ObjectId> = fields
+        .map(|fields| {
+            fields
+                .iter()
+                .filter_map(|f| f.as_reference().ok())
+                .collect()
+        })
+        .unwrap_or_default();
+    let mut visited = BTreeSet::new();
+    while let Some(object) = stack.pop() {
+        // a broken form can refer back to a field above, which would loop forever
+        if !visited.insert(object) {
+            continue;
+        }
+        let Ok(field) = document.get_dictionary(object) else {
+            continue;
+        };
+        add(object, field, true, None);
+        if let Ok(kids) = field.get(b"Kids").and_then(Object::as_array) {
+            stack.extend(kids.iter().filter_map(|kid| kid.as_reference().ok()));
+        }
+    }
+
+    texts
+}
+
+/// The characters of PDFDocEncoding that differ from Latin-1, as `(byte, character)`. The
+/// bytes 0x9F and 0xAD are undefined and decode to the replacement character.
+const PDF_DOC_ENCODING: &[(u8, char)] = &[
+    (0x18, '\u{02D8}'),
+    (0x19, '\u{02C7}'),
+    (0x1A, '\u{02C6}'),
+    (0x1B, '\u{02D9}'),
+    (0x1C, '\u{02DD}'),
+    (0x1D, '\u{02DB}'),
+    (0x1E, '\u{02DA}'),
+    (0x1F, '\u{02DC}'),
+    (0x80, '\u{2022}'),
+    (0x81, '\u{2020}'),
+    (0x82, '\u{2021}'),
+    (0x83, '\u{2026}'),
+    (0x84, '\u{2014}'),
+    (0x85, '\u{2013}'),
+    (0x86, '\u{0192}'),
+    (0x87, '\u{2044}'),
+    (0x88, '\u{2039}'),
+    (0x89, '\u{203A}'),
+    (0x8A, '\u{2212}'),
+    (0x8B, '\u{2030}'),
+    (0x8C, '\u{201E}'),
+    (0x8D, '\u{201C}'),
+    (0x8E, '\u{201D}'),
+    (0x8F, '\u{2018}'),
+    (0x90, '\u{2019}'),
+    (0x91, '\u{201A}'),
+    (0x92, '\u{2122}'),
+    (0x93, '\u{FB01}'),
+    (0x94, '\u{FB02}'),
+    (0x95, '\u{0141}'),
+    (0x96, '\u{0152}'),
+    (0x97, '\u{0160}'),
+    (0x98, '\u{0178}'),
+    (0x99, '\u{017D}'),
+    (0x9A, '\u{0131}'),
+    (0x9B, '\u{0142}'),
+    (0x9C, '\u{0153}'),
+    (0x9D, '\u{0161}'),
+    (0x9E, '\u{017E}'),
+    (0x9F, char::REPLACEMENT_CHARACTER),
+    (0xA0, '\u{20AC}'),
+    (0xAD, char::REPLACEMENT_CHARACTER),
+];
+
+/// Decodes a pdf text string, which is UTF-16 or, in pdf 2.0, UTF-8 when it starts with a
+/// byte order mark and PDFDocEncoding otherwise.
+fn decode_text_string(bytes: &[u8]) -> String {
+    match bytes {
+        [0xFE, 0xFF, rest @ ..] => {
+            let units: Vec<u16> = rest
+                .chunks_exact(2)
+                .map(|pair| u16::from_be_bytes([pair[0], pair[1]]))
+                .collect();
+            String::from_utf16_lossy(&units)
+        }
+        [0xEF, 0xBB, 0xBF, rest @ ..] => String::from_utf8_lossy(rest).to_string(),
+        _ => bytes
+            .iter()
+            .map(|&byte| {
+                PDF_DOC_ENCODING
+                    .iter()
+                    .find(|&&(code, _)| code == byte)
+                    .map_or(char::from(byte), |&(_, character)| character)
+            })
+            .collect(),
+    }
+}
+
+/// Encodes `text` as a pdf text string, ASCII as it is and anything else as UTF-16.
+fn encode_text_string(text: &str) -> Object {
+    if text.is_ascii() {
+        return Object::string_literal(text);
+    }
+    let mut bytes = vec![0xFE, 0xFF];
+    for unit in text.encode_utf16() {
+        bytes.extend(unit.to_be_bytes());
+    }
+    Object::String(bytes, StringFormat::Hexadecimal)
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_text_strings() {
+        let encoded = encode_text_string("Fyll i ditt företag");
+        assert_eq!(
+            decode_text_string(encoded.as_str().unwrap()),
+            "Fyll i ditt företag"
+        );
+        assert_eq!(encode_text_string("Name"), Object::string_literal("Name"));
+        assert_eq!(decode_text_string(b"Caf\xe9"), "Café");
+        assert_eq!(
+            decode_text_string(b"\x8dQuoted\x8e \x84 5 \xa0\x83"),
+            "“Quoted” — 5 €…"
+        );
+    }
+
+    #[test]
+    fn test_read_and_save() {
+        use lopdf::dictionary;
+
+        // a page with a comment and a field, and a form with a group of fields above it
+        let mut document = lopdf::Document::with_version("1.5");
+        let pages_id = document.new_object_id();
+        let comment_id = document.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "FreeText",
+            "Contents" => Object::string_literal("Sign here"),
+            "AP" => dictionary! {},
+        });
+        let link_id = document.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Link",
+            "Contents" => Object::string_literal("https://example.com"),
+        });
+        let group_id = document.new_object_id();
+        let field_id = document.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Widget",
+            "Parent" => group_id,
+            "T" => Object::string_literal("company"),
+            "TU" => Object::string_literal("Company name"),
+            "V" => Object::string_literal("ACME AB"),
+        });
+        document.objects.insert(
+            group_id,
+            Object::Dictionary(dictionary! {
+                "T" => Object::string_literal("supplier"),
+                "TU" => Object::string_literal("Supplier details"),
+                "Kids" => vec![field_id.into()],
+            }),
+        );
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Annots" => vec![comment_id.into(), link_id.into(), field_id.into()],
+        });
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let form_id = document.add_object(dictionary! {
+            "Fields" => vec![group_id.into()],
+        });
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "AcroForm" => form_id,
+        });
+        document.trailer.set("Root", catalog_id);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("form.pdf");
+        document.save(&path).unwrap();
+
+        let annotations = Annotations::read(&path, None).unwrap();
+        let texts: Vec<(&str, bool, Option<usize>)> = annotations
+            .texts
+            .iter()
+            .map(|text| (text.text.as_str(), text.tooltip, text.page))
+            .collect();
+        assert_eq!(
+            texts,
+            vec![
+                ("Sign here", false, Some(1)),
+                ("Company name", true, Some(1)),
+                ("Supplier details", true, None),
+            ]
+        );
+
+        let translations: Vec<String> = annotations
+            .texts
+            .iter()
+            .map(|text| format!("{} (sv)", text.text))
+            .collect();
+        let translated_path = dir.path().join("form.sv.pdf");
+        annotations
+            .save(&translated_path, &translations, None)
+            .unwrap();
+
+        let translated = lopdf::Document::load(&translated_path).unwrap();
+        let field = translated.get_dictionary(field_id).unwrap();
+        assert_eq!(
+            field.get(b"TU").unwrap().as_str().unwrap(),
+            b"Company name (sv)"
+        );
+        // the name and value of the field are left as they were
+        assert_eq!(field.get(b"T").unwrap().as_str().unwrap(), b"company");
+        assert_eq!(field.get(b"V").unwrap().as_str().unwrap(), b"ACME AB");
+        let comment = translated.get_dictionary(comment_id).unwrap();
+        assert!(!comment.has(b"AP"));
+        let link = translated.get_dictionary(link_id).unwrap();
+        assert_eq!(
+            link.get(b"Contents").unwrap().as_str().unwrap(),
+            b"https://example.com"
+        );
+    }
+
+    #[test]
+    fn test_save_metadata() {
+        use lopdf::dictionary;
+
+        // an outline of a chapter with a section, followed by a second chapter
+        let mut document = lopdf::Document::with_version("1.5");
+        let pages_id = document.add_object(dictionary! {
+            "Type" => "Pages",
+            "Kids" => Vec::<Object>::new(),
+            "Count" => 0,
+        });
+        let outlines_id = document.new_object_id();
+        let (first_id, section_id, second_id) = (
+            document.new_object_id(),
+            document.new_object_id(),
+            document.new_object_id(),
+        );
+        for (id, item) in [
+            (
+                first_id,
+                dictionary! {
+                    "Title" => Object::string_literal("Introduction"),
+                    "Parent" => outlines_id,
+                    "First" => section_id,
+                    "Last" => section_id,
+                    "Next" => second_id,
+                },
+            ),
+            (
+                section_id,
+                dictionary! {
+                    "Title" => Object::string_literal("Background "),
+                    "Parent" => first_id,
+                },
+            ),
+            (
+                second_id,
+                dictionary! {
+                    "Title" => Object::string_literal("Results"),
+                    "Parent" => outlines_id,
+                    "Prev" => first_id,
+                },
+            ),
+            (
+                outlines_id,
+                dictionary! {
+                    "Type" => "Outlines",
+                    "First" => first_id,
+                    "Last" => second_id,
+                },
+            ),
+        ] {
+            document.objects.insert(id, Object::Dictionary(item));
+        }
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+            "Outlines" => outlines_id,
+        });
+        document.trailer.set("Root", catalog_id);
+        let info_id = document.add_object(dictionary! {
+            "Title" => Object::string_literal("Annual report"),
+            "Author" => Object::string_literal("Jane Doe"),
+        });
+        document.trailer.set("Info", info_id);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("report.pdf");
+        document.save(&path).unwrap();
+
+        let entry = |title: &str, children| OutlineEntry {
+            title: title.to_string(),
+            page: None,
+            children,
+        };
+        let original = Metadata {
+            title: Some("Annual report".to_string()),
+            subject: None,
+            keywords: Some("finance".to_string()),
+            outline: vec![
+                entry("Introduction", vec![entry("Background", Vec::new())]),
+                entry("Results", Vec::new()),
+            ],
+        };
+        let translated = Metadata {
+            title: Some("Årsredovisning".to_string()),
+            subject: None,
+            keywords: Some("ekonomi".to_string()),
+            outline: vec![
+                entry("Inledning", vec![entry("Bakgrund", Vec::new())]),
+                entry("Resultat", Vec::new()),
+            ],
+        };
+        let annotations = Annotations::read(&path, None).unwrap();
+        assert!(annotations.is_empty());
+        let translated_path = dir.path().join("report.sv.pdf");
+        annotations
+            .save(&translated_path, &[], Some((&original, &translated)))
+            .unwrap();
+
+        let saved = lopdf::Document::load(&translated_path).unwrap();
+        let text = |id: ObjectId, key: &[u8]| {
+            decode_text_string(
+                saved
+                    .get_dictionary(id)
+                    .unwrap()
+                    .get(key)
+                    .unwrap()
+                    .as_str()
+                    .unwrap(),
+            )
+        };
+        assert_eq!(text(info_id, b"Title"), "Årsredovisning");
+        assert_eq!(text(info_id, b"Keywords"), "ekonomi");
+        assert_eq!(text(info_id, b"Author"), "Jane Doe");
+        assert_eq!(text(first_id, b"Title"), "Inledning");
+        assert_eq!(text(section_id, b"Title"), "Bakgrund");
+        assert_eq!(text(second_id, b"Title"), "Resultat");
+    }
+
+    #[test]
+    fn test_read_encrypted() {
+        use lopdf::dictionary;
+
+        // the bytes a password is padded with, encrypted into the check of the user password
+        const PADDING: [u8; 32] = [
+            0x28, 0xBF, 0x4E, 0x5E, 0x4E, 0x75, 0x8A, 0x41, 0x64, 0x00, 0x4E, 0x56, 0xFF, 0xFA,
+            0x01, 0x08, 0x2E, 0x2E, 0x00, 0xB6, 0xD0, 0x68, 0x3E, 0x80, 0x2F, 0x0C, 0xA9, 0xFE,
+            0x64, 0x53, 0x69, 0x7A,
+        ];
+        // encrypts or decrypts with RC4, which is the same operation
+        let rc4 = |key: &[u8], data: &[u8]| -> Vec<u8> {
+            let mut state: Vec<u8> = (0..=255).collect();
+            let mut j = 0u8;
+            for i in 0..256 {
+                j = j.wrapping_add(state[i]).wrapping_add(key[i % key.len()]);
+                state.swap(i, j as usize);
+            }
+            let (mut i, mut j) = (0u8, 0u8);
+            data.iter()
+                .map(|byte| {
+                    i = i.wrapping_add(1);
+                    j = j.wrapping_add(state[i as usize]);
+                    state.swap(i as usize, j as usize);
+                    byte ^ state[state[i as usize].wrapping_add(state[j as usize]) as usize]
+                })
+                .collect()
+        };
+
+        // a pdf encrypted with 40 bit RC4 and the user password "secret"
+        let mut document = lopdf::Document::with_version("1.4");
+        let encrypt_id = document.add_object(dictionary! {
+            "Filter" => "Standard",
+            "V" => 1,
+            "R" => 2,
+            "Length" => 40,
+            "O" => Object::String(vec![0x42; 32], StringFormat::Hexadecimal),
+            "P" => -4,
+        });
+        document.trailer.set("Encrypt", encrypt_id);
+        document.trailer.set(
+            "ID",
+            vec![
+                Object::String(b"0123456789abcdef".to_vec(), StringFormat::Hexadecimal),
+                Object::String(b"0123456789abcdef".to_vec(), StringFormat::Hexadecimal),
+            ],
+        );
+        let key = lopdf::encryption::get_encryption_key(&document, "secret", false).unwrap();
+        document.get_dictionary_mut(encrypt_id).unwrap().set(
+            "U",
+            Object::String(rc4(&key, &PADDING), StringFormat::Hexadecimal),
+        );
+        let encrypt = |id: ObjectId, text: &str| {
+            let encrypted =
+                lopdf::encryption::decrypt_object(&key, id, &Object::string_literal(text)).unwrap();
+            Object::String(encrypted, StringFormat::Hexadecimal)
+        };
+
+        let pages_id = document.new_object_id();
+        let comment_id = document.new_object_id();
+        let field_id = document.new_object_id();
+        let info_id = document.new_object_id();
+        document.objects.insert(
+            comment_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Text",
+                "Contents" => encrypt(comment_id, "Sign here"),
+            }),
+        );
+        document.objects.insert(
+            field_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Widget",
+                "T" => encrypt(field_id, "company"),
+                "TU" => encrypt(field_id, "Company name"),
+            }),
+        );
+        document.objects.insert(
+            info_id,
+            Object::Dictionary(dictionary! {
+                "Title" => encrypt(info_id, "Order form"),
+            }),
+        );
+        document.trailer.set("Info", info_id);
+        let page_id = document.add_object(dictionary! {
+            "Type" => "Page",
+            "Parent" => pages_id,
+            "MediaBox" => vec![0.into(), 0.into(), 595.into(), 842.into()],
+            "Annots" => vec![comment_id.into(), field_id.into()],
+        });
+        document.objects.insert(
+            pages_id,
+            Object::Dictionary(dictionary! {
+                "Type" => "Pages",
+                "Kids" => vec![page_id.into()],
+                "Count" => 1,
+            }),
+        );
+        let catalog_id = document.add_object(dictionary! {
+            "Type" => "Catalog",
+            "Pages" => pages_id,
+        });
+        document.trailer.set("Root", catalog_id);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("form.pdf");
+        document.save(&path).unwrap();
+
+        assert!(matches!(
+            Annotations::read(&path, None),
+            Err(Error::Encrypted {
+                password_given: false,
+                ..
+            })
+        ));
+        assert!(matches!(
+            Annotations::read(&path, Some("wrong")),
+            Err(Error::Extract { .. })
+        ));
+
+        let annotations = Annotations::read(&path, Some("secret")).unwrap();
+        let texts: Vec<&str> = annotations
+            .texts
+            .iter()
+            .map(|text| text.text.as_str())
+            .collect();
+        assert_eq!(texts, vec!["Sign here", "Company name"]);
+        let field = annotations.document.get_dictionary(field_id).unwrap();
+        assert_eq!(field.get(b"T").unwrap().as_str().unwrap(), b"company");
+        // the document information is decrypted once, not twice
+        let info = annotations.document.get_dictionary(info_id).unwrap();
+        assert_eq!(info.get(b"Title").unwrap().as_str().unwrap(), b"Order form");
+    }
+}
@@ -0,0 +1,192 @@
+//! The `batch` module which expands directories and glob patterns into the pdf files to translate
+//! and summarises the results of translating them.
+
+use crate::error;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+/// A pdf file found while expanding `--path`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InputFile {
+    pub path: PathBuf,
+    /// The directory of the file relative to the expanded directory or glob base,
+    /// used to mirror the input tree in the output directory.
+    pub relative_dir: PathBuf,
+}
+
+/// The outcome of translating a single file in a batch.
+pub struct FileReport {
+    pub path: PathBuf,
+    pub result: error::Result<(usize, usize)>,
+}
+
+fn is_glob(path: &str) -> bool {
+    path.contains(['*', '?', '['])
+}
+
+fn is_pdf(path: &Path) -> bool {
+    path.extension()
+        .map(|ext| ext.eq_ignore_ascii_case("pdf"))
+        .unwrap_or(false)
+}
+
+/// The leading part of a glob pattern that does not contain any wildcards.
+fn glob_base(pattern: &str) -> PathBuf {
+    let mut base = PathBuf::new();
+    for component in Path::new(pattern).components() {
+        if let Component::Normal(part) = component {
+            if is_glob(&part.to_string_lossy()) {
+                break;
+            }
+        }
+        base.push(component);
+    }
+    base
+}
+
+fn relative_dir(path: &Path, base: &Path) -> PathBuf {
+    path.parent()
+        .and_then(|parent| parent.strip_prefix(base).ok())
+        .map(Path::to_path_buf)
+        .unwrap_or_default()
+}
+
+fn walk(dir: &Path, recursive: bool, files: &mut Vec<PathBuf>) -> error::Result<()> {
+    for entry in fs::read_dir(dir).map_err(|e| error::Error::io(dir, e))? {
+        let path = entry.map_err(|e| error::Error::io(dir, e))?.path();
+        if path.is_dir() {
+            if recursive {
+                walk(&path, recursive, files)?;
+            }
+        } else if is_pdf(&path) {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Expands `path` into the pdf files it refers to.
+///
+/// `path` can be a single file, a directory (only searched recursively when `recursive` is
+/// set) or a glob pattern such as `papers/**/*.pdf`.
+pub fn collect_inputs(path: &str, recursive: bool) -> error::Result<Vec<InputFile>> {
+    let as_path = Path::new(path);
+
+    let (base, mut files) = if as_path.is_file() {
+        (
+            as_path.parent().map(Path::to_path_buf).unwrap_or_default(),
+            vec![as_path.to_path_buf()],
+        )
+    } else if as_path.is_dir() {
+        let mut files = Vec::new();
+        walk(as_path, recursive, &mut files)?;
+        (as_path.to_path_buf(), files)
+    } else if is_glob(path) {
+        let mut files = Vec::new();
+        let entries = glob::glob(path)
+            .map_err(|e| error::Error::Input(format!("Invalid pattern '{}': {}", path, e)))?;
+        for entry in entries {
+            let entry = entry.map_err(|e| {
+                let path = e.path().to_path_buf();
+                error::Error::io(&path, e.into())
+            })?;
+            if entry.is_file() && is_pdf(&entry) {
+                files.push(entry);
+            }
+        }
+        (glob_base(path), files)
+    } else {
+        return Err(error::Error::Input(format!(
+            "No such file or directory: '{}'",
+            path
+        )));
+    };
+
+    files.sort();
+    Ok(files
+        .into_iter()
+        .map(|path| InputFile {
+            relative_dir: relative_dir(&path, &base),
+            path,
+        })
+        .collect())
+}
+
+pub fn print_summary(reports: &[FileReport]) {
+    const FILE_WIDTH: usize = 50;
+    const STATUS_WIDTH: usize = 7;
+    const NUMBER_WIDTH: usize = 10;
+
+    println!(
+        "{:<FILE_WIDTH$} | {:<STATUS_WIDTH$} | {:>NUMBER_WIDTH$} | {:>NUMBER_WIDTH$}",
+        "File", "Status", "Pages", "Characters"
+    );
+    println!(
+        "{:-<FILE_WIDTH$}-+-{:-<STATUS_WIDTH$}-+-{:-<NUMBER_WIDTH$}-+-{:-<NUMBER_WIDTH$}",
+        "", "", "", ""
+    );
+
+    let (mut succeeded, mut pages, mut characters) = (0, 0, 0);
+    for report in reports {
+        let name = report.path.display().to_string();
+        match &report.result {
+            Ok((file_pages, file_characters)) => {
+                succeeded += 1;
+                pages += file_pages;
+                characters += file_characters;
+                println!(
+                    "{:<FILE_WIDTH$} | {:<STATUS_WIDTH$} | {:>NUMBER_WIDTH$} | {:>NUMBER_WIDTH$}",
+                    name, "ok", file_pages, file_characters
+                );
+            }
+            Err(_) => println!(
+                "{:<FILE_WIDTH$} | {:<STATUS_WIDTH$} | {:>NUMBER_WIDTH$} | {:>NUMBER_WIDTH$}",
+                name, "failed", "-", "-"
+            ),
+        }
+    }
+
+    println!(
+        "\n{} succeeded, {} failed, {} pages, {} characters",
+        succeeded,
+        reports.len() - succeeded,
+        pages,
+        characters
+    );
+
+    for report in reports {
+        if let Err(e) = &report.result {
+            println!("{}: {}", report.path.display(), e);
+        }
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_glob_base() {
+        assert_eq!(glob_base("papers/**/*.pdf"), PathBuf::from("papers"));
+        assert_eq!(glob_base("*.pdf"), PathBuf::new());
+        assert_eq!(
+            glob_base("/data/in/2023-*/a.pdf"),
+            PathBuf::from("/data/in")
+        );
+    }
+
+    #[test]
+    fn test_collect_directory() {
+        let dir = format!("{}/test-files", env!("CARGO_MANIFEST_DIR"));
+        let inputs = collect_inputs(&dir, false).expect("Error collecting inputs");
+        assert_eq!(inputs.len(), 6);
+        assert!(inputs
+            .iter()
+            .all(|input| input.relative_dir == PathBuf::new()));
+
+        let pattern = format!("{}/test-files/W*.pdf", env!("CARGO_MANIFEST_DIR"));
+        let inputs = collect_inputs(&pattern, false).expect("Error collecting inputs");
+        assert_eq!(inputs.len(), 1);
+        assert!(inputs[0].path.ends_with("Winch (2004).pdf"));
+    }
+}
@@ -0,0 +1,212 @@
+//! The `cache` module which remembers translated lines, so text that repeats within or across
+//! documents, such as headers and footers, is only sent to the backend once.
+
+use crate::error;
+use directories::ProjectDirs;
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+/// A cache shared by the translations running in parallel.
+pub type SharedCache = Arc<Mutex<TranslationCache>>;
+
+#[derive(Debug, Default)]
+pub struct TranslationCache {
+    path: PathBuf,
+    entries: HashMap<String, String>,
+    changed: bool,
+}
+
+impl TranslationCache {
+    /// Loads the cache from the default cache file, starting empty if it does not exist.
+    pub fn load() -> error::Result<TranslationCache> {
+        let path = Self::get_cache_path()?;
+        let entries = match fs::read_to_string(&path) {
+            Ok(contents) => serde_json::from_str(&contents).unwrap_or_default(),
+            Err(_) => HashMap::new(),
+        };
+
+        Ok(TranslationCache {
+            path,
+            entries,
+            changed: false,
+        })
+    }
+
+    pub fn shared(self) -> SharedCache {
+        Arc::new(Mutex::new(self))
+    }
+
+    /// `scope` identifies the backend and settings such as the glossary, since they change
+    /// the translation of the same text.
+    fn key(scope: &str, source: &str, target: &str, text: &str) -> String {
+        let digest = Sha256::digest(format!("{}\0{}\0{}\0{}", scope, source, target, text));
+        format!("{:x}", digest)
+    }
+
+    pub fn get(&self, scope: &str, source: &str, target: &str, text: &str) -> Option<String> {
+        self.entries
+            .get(&Self::key(scope, source, target, text))
+            .cloned()
+    }
+
+    pub fn insert(
+        &mut self,
+        scope: &str,
+        source: &str,
+        target: &str,
+        text: &str,
+        translation: &str,
+    ) {
+        self.entries.insert(
+            Self::key(scope, source, target, text),
+            translation.to_string(),
+        );
+        self.changed = true;
+    }
+
+    /// Writes the cache to disk if anything was added since it was loaded.
+    pub fn save(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.changed {
+            fs::write(&self.path, serde_json::to_string(&self.entries)?)?;
+            self.changed = false;
+        }
+        Ok(())
+    }
+
+    fn get_cache_path() -> std::io::Result<PathBuf> {
+        Ok(get_cache_dir()?.join("translations.json"))
+    }
+}
+
+/// Prints where the cache is and what it holds.
+pub fn info() -> error::Result<()> {
+    let dir = get_cache_dir()?;
+    let cache = TranslationCache::load()?;
+    let count = |dir: &Path, prefix: &str| {
+        fs::read_dir(dir)
+            .map(|entries| {
+                entries
+                    .filter_map(Result::ok)
+                    .filter(|entry| entry.file_name().to_string_lossy().starts_with(prefix))
+                    .count()
+            })
+            .unwrap_or(0)
+    };
+
+    println!("Cache directory:         {}", dir.display());
+    println!(
+        "Translated lines:        {} ({})",
+        cache.entries.len(),
+        format_size(fs::metadata(&cache.path).map(|m| m.len()).unwrap_or(0))
+    );
+    println!("Unfinished translations: {}", count(&dir.join("jobs"), ""));
+    println!("Language lists:          {}", count(&dir, "languages-"));
+    println!("Access tokens:           {}", count(&dir, "token-"));
+    println!("Total size:              {}", format_size(dir_size(&dir)));
+    Ok(())
+}
+
+/// Removes the cached translations and language lists, and with `all` the progress of
+/// unfinished translations and the cached access tokens as well.
+pub fn clear(all: bool) -> error::Result<()> {
+    let dir = get_cache_dir()?;
+    let mut removed = 0;
+    for entry in fs::read_dir(&dir).map_err(|e| error::Error::io(&dir, e))? {
+        let path = entry.map_err(|e| error::Error::io(&dir, e))?.path();
+        let name = path
+            .file_name()
+            .unwrap_or_default()
+            .to_string_lossy()
+            .to_string();
+        let result = if name == "translations.json" || name.starts_with("languages-") {
+            fs::remove_file(&path)
+        } else if all && path.is_dir() {
+            fs::remove_dir_all(&path)
+        } else if all {
+            fs::remove_file(&path)
+        } else {
+            continue;
+        };
+        result.map_err(|e| error::Error::io(&path, e))?;
+        removed += 1;
+    }
+    println!("Removed {} entries from {}", removed, dir.display());
+    Ok(())
+}
+
+/// The total size of the files in `dir` and its subdirectories.
+pub fn dir_size(dir: &Path) -> u64 {
+    fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .filter_map(Result::ok)
+                .map(|entry| match entry.metadata() {
+                    Ok(metadata) if metadata.is_dir() => dir_size(&entry.path()),
+                    Ok(metadata) => metadata.len(),
+                    Err(_) => 0,
+                })
+                .sum()
+        })
+        .unwrap_or(0)
+}
+
+/// Formats a number of bytes for people, e.g. `1.5 MiB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+/// Determines the cache directory using the `directories` crate, creating it if needed.
+pub fn get_cache_dir() -> std::io::Result<PathBuf> {
+    let proj_dirs = ProjectDirs::from("com", "pdf_translator_company", "PDF Translator")
+        .ok_or_else(|| {
+            std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                "Failed to get project directories",
+            )
+        })?;
+    let cache_dir = proj_dirs.cache_dir();
+    if !cache_dir.exists() {
+        fs::create_dir_all(cache_dir)?;
+    }
+    Ok(cache_dir.to_path_buf())
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_cache_is_per_language_pair() {
+        let mut cache = TranslationCache::default();
+        cache.insert("google-v2", "en", "sv", "Hello", "Hej");
+
+        assert_eq!(
+            cache.get("google-v2", "en", "sv", "Hello"),
+            Some("Hej".to_string())
+        );
+        assert_eq!(cache.get("google-v2", "en", "fi", "Hello"), None);
+        assert_eq!(cache.get("google-v2", "en", "sv", "Hello!"), None);
+        assert_eq!(cache.get("google-v3", "en", "sv", "Hello"), None);
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KiB");
+        assert_eq!(format_size(5 * 1024 * 1024), "5.0 MiB");
+    }
+}
@@ -0,0 +1,1152 @@
+//! The configuration: named profiles holding a backend, its credentials and the defaults used
+//! when translating, with a project-local config file and environment variables layered over them.
+
+use crate::auth;
+use crate::error;
+use crate::languages;
+use crate::output;
+use crate::secrets;
+use crate::status;
+use crate::translator;
+use clap::ValueEnum;
+use directories::ProjectDirs;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+/// The profile used when none is selected, and the one older config files are migrated to.
+pub const DEFAULT_PROFILE: &str = "default";
+
+/// The keys of a profile that can be read and changed with `config get`, `set` and `unset`.
+pub const KEYS: &[&str] = &[
+    "api_key",
+    "project_id",
+    "access_token",
+    "service_account_key",
+    "backend",
+    "location",
+    "model",
+    "glossary",
+    "endpoint",
+    "source",
+    "target",
+    "format",
+    "name_template",
+];
+
+/// The keys whose values are secrets, see the `secrets` module.
+const SECRET_KEYS: &[&str] = &["api_key", "access_token"];
+
+/// The project-local config file, looked up in the current directory and its parents.
+pub const PROJECT_FILE: &str = ".pdf-translator.toml";
+
+/// Selects the profile when `--profile` is not given.
+const PROFILE_ENV: &str = "PDF_TRANSLATOR_PROFILE";
+
+/// The profile selected with `--profile` for this run.
+static SELECTED_PROFILE: OnceLock<String> = OnceLock::new();
+
+/// Selects the profile used instead of the default one for the rest of the run.
+pub fn select_profile(name: &str) {
+    let _ = SELECTED_PROFILE.set(name.to_string());
+}
+
+/// The named profiles and which of them is used by default, together with the layers
+/// applied over the profile in use: the project-local config file, then the environment.
+/// Only the profiles are saved.
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct Config {
+    #[serde(default)]
+    default_profile: String,
+    #[serde(default)]
+    profiles: BTreeMap<String, Profile>,
+    #[serde(skip)]
+    project: Option<(PathBuf, ProjectFile)>,
+    /// The settings given as `PDF_TRANSLATOR_<KEY>` environment variables
+    #[serde(skip)]
+    environment: Profile,
+}
+
+/// A `.pdf-translator.toml` file, which pins settings for everything below its directory.
+#[derive(Debug, Deserialize, Clone, Default)]
+struct ProjectFile {
+    /// The profile the settings are applied over
+    #[serde(default)]
+    profile: String,
+    #[serde(flatten)]
+    settings: Profile,
+}
+
+/// A backend with its credentials and settings, and the defaults used when translating with it.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct Profile {
+    /// A reference to the API key, see the `secrets` module
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    api_key: String,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    project_id: String,
+    /// A reference to the access token, see the `secrets` module
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    access_token: String,
+    /// Path to a service account JSON key used to mint access tokens
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    service_account_key: String,
+    /// The translation backend, `google-v2` when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    backend: String,
+    /// The Google Cloud location used by the v3 backend, `global` when empty
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    location: String,
+    /// The model used by the v3 backend, e.g. `general/nmt` or a custom AutoML model ID
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    model: String,
+    /// The glossary used by the v3 backend, as an ID or a full resource name
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    glossary: String,
+    /// The base URL of the translation API, e.g. a proxy or a mock server for testing
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    endpoint: String,
+    /// The source language used when `--source` is not given
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    source: String,
+    /// The target languages used when `--target` is not given
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    target: Vec<String>,
+    /// The output format used when `--format` is not given
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    format: Option<output::OutputFormat>,
+    /// The naming template used when `--name-template` is not given
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    name_template: String,
+}
+
+/// Keeps the previously saved value of a field that was not given this time.
+fn keep_previous(field: &mut String, previous: String, _name: &str) {
+    if field.is_empty() && !previous.is_empty() {
+        *field = previous;
+        #[cfg(debug_assertions)]
+        {
+            println!("Updating {} to match old config", _name);
+        }
+    }
+}
+
+impl Profile {
+    pub fn new(
+        api_key: String,
+        project_id: String,
+        access_token: String,
+        service_account_key: String,
+    ) -> Profile {
+        Profile {
+            api_key,
+            project_id,
+            access_token,
+            service_account_key,
+            ..Profile::default()
+        }
+    }
+
+    /// Sets the backend and its settings, empty values keep what was saved before.
+    pub fn with_backend(
+        mut self,
+        backend: Option<translator::Backend>,
+        location: String,
+        model: String,
+        glossary: String,
+    ) -> Profile {
+        self.backend = backend
+            .map(|backend| backend.name().to_string())
+            .unwrap_or_default();
+        self.location = location;
+        self.model = model;
+        self.glossary = glossary;
+        self
+    }
+
+    /// Sets the defaults used when translating, empty values keep what was saved before.
+    pub fn with_defaults(
+        mut self,
+        source: String,
+        target: Vec<String>,
+        format: Option<output::OutputFormat>,
+        name_template: String,
+    ) -> Profile {
+        self.source = source;
+        self.target = target;
+        self.format = format;
+        self.name_template = name_template;
+        self
+    }
+
+    /// Whether no field of the profile is set.
+    pub fn is_empty(&self) -> bool {
+        *self == Profile::default()
+    }
+
+    fn field(&self, key: &str) -> Option<&String> {
+        match key {
+            "api_key" => Some(&self.api_key),
+            "project_id" => Some(&self.project_id),
+            "access_token" => Some(&self.access_token),
+            "service_account_key" => Some(&self.service_account_key),
+            "backend" => Some(&self.backend),
+            "location" => Some(&self.location),
+            "model" => Some(&self.model),
+            "glossary" => Some(&self.glossary),
+            "endpoint" => Some(&self.endpoint),
+            "source" => Some(&self.source),
+            "name_template" => Some(&self.name_template),
+            _ => None,
+        }
+    }
+
+    fn field_mut(&mut self, key: &str) -> Option<&mut String> {
+        match key {
+            "api_key" => Some(&mut self.api_key),
+            "project_id" => Some(&mut self.project_id),
+            "access_token" => Some(&mut self.access_token),
+            "service_account_key" => Some(&mut self.service_account_key),
+            "backend" => Some(&mut self.backend),
+            "location" => Some(&mut self.location),
+            "model" => Some(&mut self.model),
+            "glossary" => Some(&mut self.glossary),
+            "endpoint" => Some(&mut self.endpoint),
+            "source" => Some(&mut self.source),
+            "name_template" => Some(&mut self.name_template),
+            _ => None,
+        }
+    }
+
+    /// The value of `key` as it is saved, secrets are only referenced or masked.
+    pub fn get(&self, key: &str) -> Result<String, String> {
+        let value = match key {
+            "target" => self.target.join(","),
+            "format" => self
+                .format
+                .and_then(|format| format.to_possible_value())
+                .map(|value| value.get_name().to_string())
+                .unwrap_or_default(),
+            _ => self.field(key).ok_or_else(|| unknown_key(key))?.clone(),
+        };
+        if SECRET_KEYS.contains(&key) {
+            Ok(secrets::mask(&value))
+        } else {
+            Ok(value)
+        }
+    }
+
+    /// Replaces the fields that are set in `layer`.
+    fn overlay(&mut self, layer: &Profile) {
+        for key in KEYS {
+            match *key {
+                "target" if !layer.target.is_empty() => self.target = layer.target.clone(),
+                "target" => {}
+                "format" => self.format = layer.format.or(self.format),
+                _ => {
+                    if let (Some(field), Some(value)) = (self.field_mut(key), layer.field(key)) {
+                        if !value.is_empty() {
+                            *field = value.clone();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    /// Sets `key` to `value`, checking that the value is valid for the key.
+    pub fn set(&mut self, key: &str, value: &str) -> Result<(), String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Err(format!("Use 'config unset {}' to clear a value", key));
+        }
+
+        match key {
+            "backend" => {
+                self.backend = translator::Backend::from_str(value, true)
+                    .map_err(|_| {
+                        format!(
+                            "Unknown backend '{}', expected one of: google-v2, google-v3",
+                            value
+                        )
+                    })?
+                    .name()
+                    .to_string()
+            }
+            "format" => {
+                self.format = Some(output::OutputFormat::from_str(value, true).map_err(|_| {
+                    format!("Unknown format '{}', expected one of: txt, json", value)
+                })?)
+            }
+            "source" if value == translator::AUTO_DETECT => self.source = value.to_string(),
+            "source" => self.source = resolve_language(value)?,
+            "target" => {
+                self.target = value
+                    .split(',')
+                    .map(resolve_language)
+                    .collect::<Result<_, _>>()?
+            }
+            "endpoint" => {
+                reqwest::Url::parse(value)
+                    .map_err(|e| format!("Invalid endpoint '{}': {}", value, e))?;
+                self.endpoint = value.to_string();
+            }
+            "service_account_key" => {
+                // store an absolute path so the key is found from any directory
+                self.service_account_key = fs::canonicalize(value)
+                    .map_err(|e| format!("Could not read '{}': {}", value, e))?
+                    .to_string_lossy()
+                    .to_string()
+            }
+            _ => *self.field_mut(key).ok_or_else(|| unknown_key(key))? = value.to_string(),
+        }
+        Ok(())
+    }
+
+    /// Clears `key`, returning the value it had.
+    pub fn clear(&mut self, key: &str) -> Result<String, String> {
+        let previous = match key {
+            "target" => std::mem::take(&mut self.target).join(","),
+            "format" => {
+                let previous = self.get(key)?;
+                self.format = None;
+                previous
+            }
+            _ => std::mem::take(self.field_mut(key).ok_or_else(|| unknown_key(key))?),
+        };
+        Ok(previous)
+    }
+
+    /// Fills in the fields that were not given from the previously saved profile.
+    fn merge(&mut self, previous: Profile) {
+        keep_previous(&mut self.api_key, previous.api_key, "api_key");
+        keep_previous(&mut self.project_id, previous.project_id, "project_id");
+        keep_previous(
+            &mut self.access_token,
+            previous.access_token,
+            "access_token",
+        );
+        keep_previous(
+            &mut self.service_account_key,
+            previous.service_account_key,
+            "service_account_key",
+        );
+        keep_previous(&mut self.backend, previous.backend, "backend");
+        keep_previous(&mut self.location, previous.location, "location");
+        keep_previous(&mut self.model, previous.model, "model");
+        keep_previous(&mut self.glossary, previous.glossary, "glossary");
+        keep_previous(&mut self.endpoint, previous.endpoint, "endpoint");
+        keep_previous(&mut self.source, previous.source, "source");
+        if self.target.is_empty() {
+            self.target = previous.target;
+        }
+        self.format = self.format.or(previous.format);
+        keep_previous(
+            &mut self.name_template,
+            previous.name_template,
+            "name_template",
+        );
+    }
+}
+
+impl Config {
+    /// Loads the configuration from the default config file.
+    pub fn load() -> error::Result<Config> {
+        let config_path = Self::get_config_path()?;
+        #[cfg(debug_assertions)]
+        {
+            dbg!(config_path.clone());
+        }
+        let config_str =
+            fs::read_to_string(&config_path).map_err(|e| error::Error::io(&config_path, e))?;
+        let mut config = Self::parse(&config_str).map_err(|e| {
+            error::Error::Config(format!("Invalid {}: {}", config_path.display(), e))
+        })?;
+        config.load_layers()?;
+        Ok(config)
+    }
+
+    /// Loads the project-local config file and the settings from the environment.
+    fn load_layers(&mut self) -> error::Result<()> {
+        if let Some(path) = find_project_file(&env::current_dir()?) {
+            let contents = fs::read_to_string(&path).map_err(|e| error::Error::io(&path, e))?;
+            let mut project: ProjectFile = toml::from_str(&contents)
+                .map_err(|e| error::Error::Config(format!("Invalid {}: {}", path.display(), e)))?;
+            // the service account key is relative to the project file
+            let key = &mut project.settings.service_account_key;
+            if !key.is_empty() {
+                if let Some(dir) = path.parent() {
+                    *key = dir.join(&*key).to_string_lossy().to_string();
+                }
+            }
+            self.project = Some((path, project));
+        }
+        self.environment = env_layer(|name| env::var(name).ok());
+        Ok(())
+    }
+
+    /// The project-local config file that is used, if any.
+    pub fn project_file(&self) -> Option<&Path> {
+        self.project.as_ref().map(|(path, _)| path.as_path())
+    }
+
+    /// Parses a config file, moving the settings of a file written before profiles
+    /// existed into the default profile.
+    fn parse(config_str: &str) -> Result<Config, Box<dyn std::error::Error>> {
+        let mut config: Config = toml::from_str(config_str)?;
+        if config.profiles.is_empty() {
+            let legacy: Profile = toml::from_str(config_str)?;
+            if !legacy.is_empty() {
+                config.profiles.insert(DEFAULT_PROFILE.to_string(), legacy);
+            }
+        }
+        Ok(config)
+    }
+
+    /// Loads the configuration, or an empty one when no config file has been saved yet,
+    /// in which case the credentials are discovered by the `auth` module.
+    pub fn load_or_default() -> error::Result<Config> {
+        let mut config = Self::load_global()?;
+        config.load_layers()?;
+        Ok(config)
+    }
+
+    /// Loads only the default config file, or an empty configuration when none has been
+    /// saved yet, without the project-local config file and the environment.
+    ///
+    /// The commands that change the configuration load it with this, so the values of the
+    /// other layers are never saved into the config file.
+    pub fn load_global() -> error::Result<Config> {
+        Self::read(&Self::get_config_path()?)
+    }
+
+    /// Reads the config file at `path`, or an empty configuration when it does not exist.
+    fn read(path: &Path) -> error::Result<Config> {
+        match fs::read_to_string(path) {
+            Ok(config_str) => Self::parse(&config_str)
+                .map_err(|e| error::Error::Config(format!("Invalid {}: {}", path.display(), e))),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Config::default()),
+            Err(e) => Err(error::Error::io(path, e)),
+        }
+    }
+
+    /// Saves the current configuration to the default config file.
+    pub fn save(&self) -> error::Result<()> {
+        let config_path = Self::get_config_path()?;
+        #[cfg(debug_assertions)]
+        {
+            dbg!(config_path.clone());
+        }
+
+        let config_str = toml::to_string(self).map_err(|e| e.to_string())?;
+        fs::write(&config_path, config_str).map_err(|e| error::Error::io(&config_path, e))?;
+        Ok(())
+    }
+
+    /// Updates the profile `name` with the fields given in `profile`, keeping the ones
+    /// that are empty. Secrets are moved to `secret_store` and only referenced.
+    pub fn update(
+        &mut self,
+        name: &str,
+        mut profile: Profile,
+        secret_store: secrets::SecretStore,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        profile.merge(self.profiles.remove(name).unwrap_or_default());
+
+        // only references to the secrets are written to the config file
+        profile.api_key =
+            secrets::store(&format!("{}.api_key", name), &profile.api_key, secret_store)?;
+        profile.access_token = secrets::store(
+            &format!("{}.access_token", name),
+            &profile.access_token,
+            secret_store,
+        )?;
+
+        self.profiles.insert(name.to_string(), profile);
+        if self.default_profile.is_empty() {
+            self.default_profile = name.to_string();
+        }
+        Ok(())
+    }
+
+    /// Makes `name` the profile used when `--profile` is not given.
+    pub fn set_default_profile(&mut self, name: &str) -> Result<(), String> {
+        if !self.has_profile(name) {
+            return Err(format!(
+                "No profile named '{}', the saved profiles are: {}",
+                name,
+                self.profile_names().join(", ")
+            ));
+        }
+        self.default_profile = name.to_string();
+        Ok(())
+    }
+
+    /// Whether a profile called `name` has been saved.
+    pub fn has_profile(&self, name: &str) -> bool {
+        self.profiles.contains_key(name)
+    }
+
+    /// The names of the saved profiles, sorted.
+    pub fn profile_names(&self) -> Vec<String> {
+        self.profiles.keys().cloned().collect()
+    }
+
+    /// The name of the profile used for this run: the one given with `--profile`, then
+    /// `PDF_TRANSLATOR_PROFILE`, the project-local config file and the saved default.
+    pub fn profile_name(&self) -> String {
+        if let Some(name) = SELECTED_PROFILE.get() {
+            return name.clone();
+        }
+        if let Ok(name) = env::var(PROFILE_ENV) {
+            if !name.is_empty() {
+                return name;
+            }
+        }
+        match &self.project {
+            Some((_, project)) if !project.profile.is_empty() => project.profile.clone(),
+            _ if !self.default_profile.is_empty() => self.default_profile.clone(),
+            _ => DEFAULT_PROFILE.to_string(),
+        }
+    }
+
+    /// The profile in use with the project-local config file and the environment applied.
+    pub fn profile(&self) -> Profile {
+        let mut profile = self
+            .profiles
+            .get(&self.profile_name())
+            .cloned()
+            .unwrap_or_default();
+        if let Some((_, project)) = &self.project {
+            profile.overlay(&project.settings);
+        }
+        profile.overlay(&self.environment);
+        profile
+    }
+
+    /// The API key, read from where the config refers to.
+    pub fn get_api_key(&self) -> String {
+        secrets::resolve("api_key", &self.profile().api_key)
+    }
+
+    /// The Google Cloud project ID, see `auth::project_id` for the one actually used.
+    pub fn get_project_id(&self) -> String {
+        self.profile().project_id
+    }
+
+    /// The fixed access token, read from where the config refers to.
+    pub fn get_access_token(&self) -> String {
+        secrets::resolve("access_token", &self.profile().access_token)
+    }
+
+    /// The path of the service account key, empty when none is configured.
+    pub fn get_service_account_key(&self) -> String {
+        self.profile().service_account_key
+    }
+
+    /// The translation backend, `google-v2` unless another one is configured.
+    pub fn get_backend(&self) -> translator::Backend {
+        match self.profile().backend.as_str() {
+            "google-v3" => translator::Backend::GoogleV3,
+            _ => translator::Backend::GoogleV2,
+        }
+    }
+
+    /// The Google Cloud location of the v3 backend, `global` unless another one is configured.
+    pub fn get_location(&self) -> String {
+        let location = self.profile().location;
+        if location.is_empty() {
+            "global".to_string()
+        } else {
+            location
+        }
+    }
+
+    /// The model of the v3 backend, empty for the default model.
+    pub fn get_model(&self) -> String {
+        self.profile().model
+    }
+
+    /// The glossary of the v3 backend, empty for none.
+    pub fn get_glossary(&self) -> String {
+        self.profile().glossary
+    }
+
+    /// The base URL of the translation API.
+    pub fn get_endpoint(&self) -> String {
+        let endpoint = self.profile().endpoint;
+        if endpoint.is_empty() {
+            translator::GOOGLE_API_ENDPOINT.to_string()
+        } else {
+            endpoint
+        }
+    }
+
+    /// The default source language, empty when none is configured.
+    pub fn get_source(&self) -> String {
+        self.profile().source
+    }
+
+    /// The default target languages, empty when none are configured.
+    pub fn get_targets(&self) -> Vec<String> {
+        self.profile().target
+    }
+
+    /// The default output format, if one is configured.
+    pub fn get_format(&self) -> Option<output::OutputFormat> {
+        self.profile().format
+    }
+
+    /// The default naming template, empty when none is configured.
+    pub fn get_name_template(&self) -> String {
+        self.profile().name_template
+    }
+
+    /// Checks the selected profile without contacting the backend.
+    pub fn check(&self) -> Report {
+        let mut report = Report::default();
+        let name = self.profile_name();
+        if name != DEFAULT_PROFILE && !self.has_profile(&name) {
+            report.errors.push(format!("No profile named '{}'", name));
+            return report;
+        }
+        let profile = self.profile();
+
+        if !profile.backend.is_empty()
+            && translator::Backend::from_str(&profile.backend, true).is_err()
+        {
+            report.errors.push(format!(
+                "backend: unknown backend '{}', expected one of: google-v2, google-v3",
+                profile.backend
+            ));
+        }
+
+        for key in SECRET_KEYS {
+            let value = profile.field(key).cloned().unwrap_or_default();
+            if env::var(secrets::env_var(key)).is_ok() {
+                continue;
+            }
+            if secrets::is_reference(&value) {
+                match secrets::lookup(&value) {
+                    Ok(secret) if secret.is_empty() => report
+                        .errors
+                        .push(format!("{}: '{}' is empty or not set", key, value)),
+                    Ok(_) => {}
+                    Err(e) => report
+                        .errors
+                        .push(format!("{}: '{}' could not be read: {}", key, value, e)),
+                }
+            } else if !value.is_empty() {
+                report.warnings.push(format!(
+                    "{}: stored in plain text in the config file, run 'config set {}' to move it to the keyring",
+                    key, key
+                ));
+            }
+        }
+
+        let credentials = auth::Credentials::discover(self);
+        if let Err(e) = &credentials {
+            report.errors.push(format!("credentials: {}", e));
+        }
+        let has_oauth = matches!(&credentials, Ok(credentials) if !matches!(credentials, auth::Credentials::None));
+
+        match self.get_backend() {
+            translator::Backend::GoogleV2 => {
+                if self.get_api_key().is_empty() && !has_oauth && credentials.is_ok() {
+                    report.errors.push("credentials: the google-v2 backend needs an API key or OAuth credentials, set 'api_key', 'access_token' or 'service_account_key', or set up Application Default Credentials".to_string());
+                }
+                for key in ["location", "model", "glossary"] {
+                    if !profile.get(key).unwrap_or_default().is_empty() {
+                        report
+                            .warnings
+                            .push(format!("{}: only used by the google-v3 backend", key));
+                    }
+                }
+            }
+            translator::Backend::GoogleV3 => {
+                if !profile.api_key.is_empty() {
+                    report
+                        .warnings
+                        .push("api_key: not used by the google-v3 backend".to_string());
+                }
+                if !has_oauth && credentials.is_ok() {
+                    report.errors.push("credentials: the google-v3 backend needs OAuth credentials, set 'access_token' or 'service_account_key', or set up Application Default Credentials".to_string());
+                }
+                if auth::project_id(self).is_empty() {
+                    report
+                        .errors
+                        .push("project_id: the google-v3 backend needs a project ID".to_string());
+                }
+            }
+        }
+
+        let supported = languages::cached();
+        if !profile.source.is_empty() && profile.source != translator::AUTO_DETECT {
+            if let Err(e) = languages::resolve_supported(&profile.source, supported.as_deref()) {
+                report.errors.push(format!("source: {}", e));
+            }
+        }
+        for target in &profile.target {
+            if let Err(e) = languages::resolve_supported(target, supported.as_deref()) {
+                report.errors.push(format!("target: {}", e));
+            }
+        }
+        if profile.target.len() > 1
+            && !profile.name_template.is_empty()
+            && !profile.name_template.contains("{target}")
+        {
+            report.errors.push(
+                "name_template: must contain '{target}' when there are several target languages"
+                    .to_string(),
+            );
+        }
+        if let Err(e) = reqwest::Url::parse(&self.get_endpoint()) {
+            report.errors.push(format!("endpoint: {}", e));
+        }
+
+        report
+    }
+
+    /// Determines the path for the configuration file.
+    pub fn get_config_path() -> error::Result<PathBuf> {
+        Ok(get_config_dir()?.join("config.toml"))
+    }
+}
+
+/// What `Config::check` found wrong with a profile, errors make the profile unusable.
+#[derive(Debug, Default)]
+pub struct Report {
+    pub errors: Vec<String>,
+    pub warnings: Vec<String>,
+}
+
+/// The nearest project-local config file in `dir` or one of its parents.
+fn find_project_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(PROJECT_FILE))
+        .find(|path| path.is_file())
+}
+
+/// The settings given as `PDF_TRANSLATOR_<KEY>` variables, e.g. `PDF_TRANSLATOR_TARGET=sv,fi`,
+/// read with `var`.
+fn env_layer(var: impl Fn(&str) -> Option<String>) -> Profile {
+    let mut profile = Profile::default();
+    for key in KEYS {
+        let name = secrets::env_var(key);
+        if let Some(value) = var(&name).filter(|value| !value.is_empty()) {
+            if let Err(e) = profile.set(key, &value) {
+                status!("Ignoring {}: {}", name, e);
+            }
+        }
+    }
+    profile
+}
+
+/// Prints the fields that are set in `profile`, naming them with `name`.
+fn print_settings(profile: &Profile, name: impl Fn(&str) -> String) {
+    for key in KEYS {
+        let value = profile.get(key).unwrap_or_default();
+        if !value.is_empty() {
+            println!("{} = \"{}\"", name(key), value);
+        }
+    }
+}
+
+/// Resolves a language to its code with the built-in table, or with the languages last
+/// fetched from the backend when the table does not know it. Whether the backend supports
+/// the language is left to [`Config::check`].
+fn resolve_language(value: &str) -> Result<String, String> {
+    languages::resolve(value)
+        .map(str::to_string)
+        .or_else(|_| languages::resolve_supported(value, languages::cached().as_deref()))
+}
+
+fn unknown_key(key: &str) -> String {
+    format!("Unknown key '{}', the keys are: {}", key, KEYS.join(", "))
+}
+
+/// The environment variable that moves the config directory, and with it the secrets file,
+/// e.g. to keep a separate configuration for CI.
+pub const CONFIG_DIR_VAR: &str = "PDF_TRANSLATOR_CONFIG_DIR";
+
+/// Determines the config directory using the `directories` crate, or [`CONFIG_DIR_VAR`]
+/// when it is set, creating it if needed.
+pub fn get_config_dir() -> error::Result<PathBuf> {
+    let config_dir = match env::var_os(CONFIG_DIR_VAR).filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => ProjectDirs::from("com", "pdf_translator_company", "PDF Translator")
+            .ok_or_else(|| error::Error::Config("Failed to get project directories".to_string()))?
+            .config_dir()
+            .to_path_buf(),
+    };
+    if !config_dir.exists() {
+        fs::create_dir_all(&config_dir).map_err(|e| error::Error::io(&config_dir, e))?;
+    }
+    Ok(config_dir)
+}
+
+/// Saves the fields given in `args` to the profile in use, keeping the ones that are empty.
+pub fn setup(mut args: Profile, secret_store: secrets::SecretStore) -> error::Result<()> {
+    if args.is_empty() {
+        return Err(error::Error::Input("You must at least provide one of the following arguments '--api-key <API_KEY>', '--access-token <ACCESS_TOKEN>', '--project-id <PROJECT_ID>', '--service-account <KEY_FILE>', '--backend <BACKEND>', '--location <LOCATION>', '--model <MODEL>', '--glossary <GLOSSARY>', '--source <LANG>', '--target <LANG>', '--format <FORMAT>', '--name-template <TEMPLATE>'".to_string()));
+    }
+
+    if !args.service_account_key.is_empty() {
+        // store an absolute path so the key is found from any directory
+        if let Ok(path) = fs::canonicalize(&args.service_account_key) {
+            args.service_account_key = path.to_string_lossy().to_string();
+        }
+        match auth::ServiceAccountKey::load(&args.service_account_key) {
+            Ok(key) => {
+                if args.project_id.is_empty() {
+                    args.project_id = key.project_id.unwrap_or_default();
+                }
+                println!("Using service account '{}'", key.client_email);
+            }
+            Err(e) => return Err(error::Error::Config(e.to_string())),
+        }
+    }
+
+    let mut config = Config::load_global()?;
+    let name = config.profile_name();
+    config.update(&name, args, secret_store)?;
+    config.save()?;
+    println!("Configuration saved to profile '{}'", name);
+    Ok(())
+}
+
+/// Makes `name` the default profile and saves the configuration.
+pub fn use_profile(name: &str) -> error::Result<()> {
+    let mut config = Config::load_global()?;
+    config.set_default_profile(name)?;
+    config.save()?;
+    println!("Now using profile '{}' by default", name);
+    Ok(())
+}
+
+/// Prints every profile and the layers applied over the one in use, with the secrets masked.
+pub fn show() -> error::Result<()> {
+    let config = Config::load_or_default()?;
+    let selected = config.profile_name();
+    if config.profiles.is_empty() {
+        println!("No configuration saved yet, credentials are discovered from the environment");
+    } else {
+        println!("default_profile = \"{}\"", config.default_profile);
+        for (name, profile) in &config.profiles {
+            let marker = if *name == selected { " (in use)" } else { "" };
+            println!("\n[profiles.{}]{}", name, marker);
+            print_settings(profile, str::to_string);
+        }
+    }
+
+    if let Some((path, project)) = &config.project {
+        println!("\n# {}", path.display());
+        if !project.profile.is_empty() {
+            println!("profile = \"{}\"", project.profile);
+        }
+        print_settings(&project.settings, str::to_string);
+    }
+    if !config.environment.is_empty() {
+        println!("\n# environment");
+        print_settings(&config.environment, secrets::env_var);
+    }
+    Ok(())
+}
+
+/// Prints the value of `key` in the selected profile.
+pub fn get(key: &str) -> error::Result<()> {
+    let config = Config::load_or_default()?;
+    println!("{}", config.profile().get(key)?);
+    Ok(())
+}
+
+/// Sets `key` in the selected profile, creating the profile if needed.
+pub fn set(key: &str, value: &str, secret_store: secrets::SecretStore) -> error::Result<()> {
+    let mut profile = Profile::default();
+    profile.set(key, value).map_err(error::Error::Input)?;
+
+    let mut config = Config::load_global()?;
+    let name = config.profile_name();
+    config.update(&name, profile, secret_store)?;
+    config.save()?;
+    println!("Set {} in profile '{}'", key, name);
+    Ok(())
+}
+
+/// Clears `key` in the selected profile, removing a secret from where it was stored.
+pub fn unset(key: &str) -> error::Result<()> {
+    let mut config = Config::load_global()?;
+    let name = config.profile_name();
+    let profile = config
+        .profiles
+        .get_mut(&name)
+        .ok_or_else(|| format!("No profile named '{}'", name))?;
+    let previous = profile.clear(key)?;
+    if SECRET_KEYS.contains(&key) {
+        secrets::delete(&previous)?;
+    }
+    config.save()?;
+    println!("Unset {} in profile '{}'", key, name);
+    Ok(())
+}
+
+/// Checks the selected profile and that the backend accepts its credentials, printing
+/// every problem found in the profile. Fails with a config error when the profile has
+/// problems, and with the error of the backend when it cannot be reached or rejects the
+/// credentials.
+pub async fn validate() -> error::Result<()> {
+    let config = Config::load_or_default()?;
+    let name = config.profile_name();
+    let backend = config.get_backend();
+    println!("Validating profile '{}' ({})", name, backend.name());
+
+    let report = config.check();
+    for warning in &report.warnings {
+        println!("warning: {}", warning);
+    }
+    for error in &report.errors {
+        println!("error: {}", error);
+    }
+    if !report.errors.is_empty() {
+        return Err(error::Error::Config(format!(
+            "{} problem(s) found in profile '{}'",
+            report.errors.len(),
+            name
+        )));
+    }
+
+    println!(
+        "Checking the credentials with the {} backend at {}",
+        backend.name(),
+        config.get_endpoint()
+    );
+    translator::fetch_languages().await?;
+    println!("The {} backend accepted the credentials", backend.name());
+    println!("Profile '{}' is valid", name);
+    Ok(())
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+    #[allow(unused_imports)]
+    use std::path::Path;
+
+    #[test]
+    fn test_save_config() {
+        /// Points the config directory, and with it the secrets file, at a temporary
+        /// directory until it is dropped, also when the test fails.
+        struct TempConfigDir(tempfile::TempDir);
+
+        impl Drop for TempConfigDir {
+            fn drop(&mut self) {
+                env::remove_var(CONFIG_DIR_VAR);
+            }
+        }
+
+        // save into a directory of its own rather than the real config and secrets files
+        let dir = TempConfigDir(tempfile::tempdir().unwrap());
+        env::set_var(CONFIG_DIR_VAR, dir.0.path());
+
+        // Test saving a dummy config
+        let mut dummy_config = Config::load_or_default().unwrap();
+        let dummy_profile = Profile::new(
+            "dummy_key".to_string(),
+            "dummy_project".to_string(),
+            "dummy_token".to_string(),
+            "".to_string(),
+        );
+        let update_result =
+            dummy_config.update(DEFAULT_PROFILE, dummy_profile, secrets::SecretStore::File);
+        assert!(update_result.is_ok());
+        let save_result = dummy_config.save();
+        assert!(save_result.is_ok());
+        assert_eq!(
+            Config::get_config_path().unwrap(),
+            dir.0.path().join("config.toml")
+        );
+        let saved = Config::load().unwrap();
+        assert_eq!(saved.profile().project_id, "dummy_project");
+
+        // the secrets went to the secrets file next to the temporary config
+        let secrets = fs::read_to_string(dir.0.path().join("secrets.toml")).unwrap();
+        assert!(secrets.contains("dummy_key"));
+        assert_eq!(
+            secrets::lookup(&format!("file:{}.api_key", DEFAULT_PROFILE)).unwrap(),
+            "dummy_key"
+        );
+    }
+
+    #[test]
+    fn test_profiles() {
+        // a config file written before profiles existed
+        let mut config = Config::parse(
+            r#"
+            api_key = "keyring:api_key"
+            project_id = "personal-project"
+            access_token = ""
+            backend = "google-v2"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.profile_names(), vec![DEFAULT_PROFILE]);
+        assert_eq!(config.profile().project_id, "personal-project");
+
+        let team = Profile::new(
+            "".to_string(),
+            "team-project".to_string(),
+            "".to_string(),
+            "".to_string(),
+        )
+        .with_defaults(
+            "de".to_string(),
+            vec!["sv".to_string(), "fi".to_string()],
+            Some(output::OutputFormat::Json),
+            "".to_string(),
+        );
+        config
+            .update("team", team, secrets::SecretStore::File)
+            .unwrap();
+        assert!(config.set_default_profile("missing").is_err());
+        config.set_default_profile("team").unwrap();
+
+        let config = Config::parse(&toml::to_string(&config).unwrap()).unwrap();
+        assert_eq!(config.default_profile, "team");
+        assert_eq!(config.get_targets(), vec!["sv", "fi"]);
+        assert_eq!(config.get_format(), Some(output::OutputFormat::Json));
+        assert_eq!(config.profiles[DEFAULT_PROFILE].api_key, "keyring:api_key");
+    }
+
+    #[test]
+    fn test_get_set_unset() {
+        let mut profile = Profile::default();
+        profile.set("backend", "Google-V3").unwrap();
+        profile.set("target", "Swedish, fi").unwrap();
+        profile.set("format", "json").unwrap();
+        profile.set("api_key", "AIzaSyD-example-key-1234").unwrap();
+
+        assert_eq!(profile.get("backend").unwrap(), "google-v3");
+        assert_eq!(profile.get("target").unwrap(), "sv,fi");
+        assert_eq!(profile.get("format").unwrap(), "json");
+        assert_eq!(profile.get("api_key").unwrap(), "********1234");
+
+        assert!(profile.set("backend", "deepl").is_err());
+        assert!(profile.set("target", "klingon").is_err());
+        assert!(profile.set("colour", "blue").is_err());
+        assert!(profile.set("model", "").is_err());
+
+        assert_eq!(profile.clear("target").unwrap(), "sv,fi");
+        assert_eq!(profile.clear("format").unwrap(), "json");
+        assert!(profile.target.is_empty() && profile.format.is_none());
+    }
+
+    #[test]
+    fn test_layers() {
+        let mut config = Config::parse(
+            r#"
+            default_profile = "personal"
+
+            [profiles.personal]
+            project_id = "personal-project"
+            target = ["de"]
+
+            [profiles.team]
+            project_id = "team-project"
+            glossary = "team-glossary"
+            target = ["fi"]
+            format = "txt"
+            "#,
+        )
+        .unwrap();
+        let project: ProjectFile = toml::from_str(
+            r#"
+            profile = "team"
+            target = ["sv", "no"]
+            glossary = "repo-glossary"
+            "#,
+        )
+        .unwrap();
+        config.project = Some((PathBuf::from(PROJECT_FILE), project));
+        config.environment = env_layer(|name| match name {
+            "PDF_TRANSLATOR_FORMAT" => Some("json".to_string()),
+            "PDF_TRANSLATOR_GLOSSARY" => Some("".to_string()),
+            _ => None,
+        });
+
+        // the project file selects the profile, and overrides it where it is set
+        assert_eq!(config.profile_name(), "team");
+        assert_eq!(config.get_project_id(), "team-project");
+        assert_eq!(config.get_targets(), vec!["sv", "no"]);
+        assert_eq!(config.get_glossary(), "repo-glossary");
+        // the environment overrides the project file, empty variables are ignored
+        assert_eq!(config.get_format(), Some(output::OutputFormat::Json));
+
+        // only the profiles are saved
+        let saved = Config::parse(&toml::to_string(&config).unwrap()).unwrap();
+        assert!(saved.project.is_none());
+        assert_eq!(saved.profiles["team"].glossary, "team-glossary");
+    }
+
+    #[test]
+    fn test_read_keeps_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("config.toml");
+        assert!(Config::read(&path).unwrap().profiles.is_empty());
+
+        fs::write(&path, "[profiles.default]\ntarget = [").unwrap();
+        let e = Config::read(&path).unwrap_err();
+        assert_eq!(e.exit_code(), error::EXIT_CONFIG);
+
+        // a project file in the directory is not layered in
+        fs::write(&path, "[profiles.default]\nproject_id = \"p1\"").unwrap();
+        fs::write(dir.path().join(PROJECT_FILE), "target = [").unwrap();
+        let config = Config::read(&path).unwrap();
+        assert!(config.project.is_none());
+        assert_eq!(config.profiles["default"].project_id, "p1");
+    }
+
+    #[test]
+    fn test_find_project_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let root = dir.path();
+        let nested = root.join("papers").join("2023");
+        fs::create_dir_all(&nested).unwrap();
+        fs::write(root.join(PROJECT_FILE), "target = [\"sv\"]").unwrap();
+
+        assert_eq!(find_project_file(&nested), Some(root.join(PROJECT_FILE)));
+    }
+
+    #[test]
+    fn test_check_reports_problems() {
+        let config = Config::parse(
+            r#"
+            [profiles.default]
+            api_key = "AIzaSyD-example-key-1234"
+            backend = "google-v3"
+            target = ["sv", "klingon"]
+            name_template = "{stem}.{ext}"
+            endpoint = "not a url"
+            "#,
+        )
+        .unwrap();
+        let report = config.check();
+
+        assert!(report.errors.iter().any(|e| e.starts_with("target:")));
+        assert!(report
+            .errors
+            .iter()
+            .any(|e| e.starts_with("name_template:")));
+        assert!(report.errors.iter().any(|e| e.starts_with("endpoint:")));
+        assert!(report
+            .warnings
+            .iter()
+            .any(|w| w == "api_key: not used by the google-v3 backend"));
+        assert!(report
+            .warnings
+            .iter()
+            .any(|w| w.starts_with("api_key: stored in plain text")));
+    }
+}
@@ -0,0 +1,290 @@
+//! The `doctor` module which checks everything a translation depends on: the text extractor,
+//! the configuration, the credentials, the backend, the cache and the output directory.
+//!
+//! The text is only ever extracted with poppler's `pdftotext`, there is no native or OCR
+//! extractor to check, so the extractor checks cover `pdftotext` and the poppler tools that
+//! `--metadata` runs.
+
+use crate::auth;
+use crate::cache;
+use crate::config;
+use crate::error::{self, Error};
+use crate::install;
+use crate::secrets;
+use crate::translator;
+use std::fs;
+use std::path::Path;
+
+/// How a single check turned out.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Status {
+    Ok,
+    /// Works, but is likely to cause problems
+    Warning,
+    /// Translating will fail until it is fixed
+    Error,
+}
+
+/// The results of the checks, printed as they are made.
+#[derive(Default)]
+struct Report {
+    problems: usize,
+    sections: usize,
+}
+
+impl Report {
+    fn section(&mut self, title: &str) {
+        if self.sections > 0 {
+            println!();
+        }
+        self.sections += 1;
+        println!("{}", title);
+    }
+
+    fn check(&mut self, status: Status, name: &str, detail: impl std::fmt::Display) {
+        const STATUS_WIDTH: usize = 7;
+        const NAME_WIDTH: usize = 14;
+
+        let label = match status {
+            Status::Ok => "ok",
+            Status::Warning => "warning",
+            Status::Error => {
+                self.problems += 1;
+                "error"
+            }
+        };
+        println!(
+            "  {:<STATUS_WIDTH$}  {:<NAME_WIDTH$}  {}",
+            label, name, detail
+        );
+    }
+}
+
+/// Runs every check and prints the results, failing if anything would stop a translation.
+///
+/// `output` is the directory translations are written to, checked for being writable.
+pub async fn run(output: &Path) -> error::Result<()> {
+    let mut report = Report::default();
+
+    report.section("Extractor");
+    match install::poppler_version() {
+        Some(version) => report.check(Status::Ok, "pdftotext", version),
+        None => report.check(
+            Status::Error,
+            "pdftotext",
+            "not found, install poppler with 'pdf-translator install'",
+        ),
+    }
+    for tool in ["pdfinfo", "pdftohtml"] {
+        match install::tool_version(tool) {
+            Some(version) => report.check(Status::Ok, tool, version),
+            None => report.check(
+                Status::Warning,
+                tool,
+                "not found, '--metadata' will fail until poppler is installed",
+            ),
+        }
+    }
+
+    report.section("Configuration");
+    let config = match config::Config::load_or_default() {
+        Ok(config) => config,
+        Err(e) => {
+            report.check(Status::Error, "config file", e);
+            return finish(report);
+        }
+    };
+    let credentials_reported = check_config(&mut report, &config);
+
+    report.section("Credentials");
+    let credentials_ok = if credentials_reported {
+        // the profile check already counted the problem with the credentials
+        report.check(
+            Status::Warning,
+            "credentials",
+            "not checked, see the profile errors above",
+        );
+        false
+    } else {
+        check_credentials(&mut report, &config).await
+    };
+
+    report.section("Backend");
+    let backend = config.get_backend();
+    if !credentials_ok {
+        report.check(
+            Status::Warning,
+            backend.name(),
+            "not checked, the credentials do not work",
+        );
+    } else {
+        match translator::fetch_languages().await {
+            Ok(languages) => report.check(
+                Status::Ok,
+                backend.name(),
+                format!(
+                    "{} answered with {} languages",
+                    config.get_endpoint(),
+                    languages.len()
+                ),
+            ),
+            Err(e) => report.check(Status::Error, backend.name(), e),
+        }
+    }
+
+    report.section("Files");
+    match cache::get_cache_dir() {
+        Ok(dir) => report.check(
+            Status::Ok,
+            "cache",
+            format!(
+                "{} ({})",
+                dir.display(),
+                cache::format_size(cache::dir_size(&dir))
+            ),
+        ),
+        Err(e) => report.check(Status::Error, "cache", e),
+    }
+    match check_writable(output) {
+        Ok(()) => report.check(Status::Ok, "output", output.display()),
+        Err(e) => report.check(Status::Error, "output", e),
+    }
+
+    finish(report)
+}
+
+fn finish(report: Report) -> error::Result<()> {
+    println!();
+    if report.problems == 0 {
+        println!("No problems found");
+        Ok(())
+    } else {
+        Err(Error::Other(format!(
+            "{} problem(s) found",
+            report.problems
+        )))
+    }
+}
+
+/// Reports the config files in use and what the selected profile sets, returning whether a
+/// problem with the credentials was among the errors.
+fn check_config(report: &mut Report, config: &config::Config) -> bool {
+    match config::Config::get_config_path() {
+        Ok(path) if path.exists() => report.check(Status::Ok, "config file", path.display()),
+        Ok(path) => report.check(
+            Status::Warning,
+            "config file",
+            format!(
+                "{} does not exist, create it with 'config setup'",
+                path.display()
+            ),
+        ),
+        Err(e) => report.check(Status::Error, "config file", e),
+    }
+    if let Some(path) = config.project_file() {
+        report.check(Status::Ok, "project file", path.display());
+    }
+
+    let name = config.profile_name();
+    let profile = config.profile();
+    let set: Vec<&str> = config::KEYS
+        .iter()
+        .copied()
+        .filter(|key| profile.get(key).is_ok_and(|value| !value.is_empty()))
+        .collect();
+    let keys = if set.is_empty() {
+        "no keys set".to_string()
+    } else {
+        set.join(", ")
+    };
+    if name != config::DEFAULT_PROFILE && !config.has_profile(&name) {
+        report.check(
+            Status::Error,
+            "profile",
+            format!("'{}' does not exist", name),
+        );
+    } else {
+        report.check(Status::Ok, "profile", format!("'{}': {}", name, keys));
+    }
+
+    let problems = config.check();
+    for warning in &problems.warnings {
+        report.check(Status::Warning, "profile", warning);
+    }
+    for error in &problems.errors {
+        report.check(Status::Error, "profile", error);
+    }
+    problems
+        .errors
+        .iter()
+        .any(|error| error.starts_with("credentials:"))
+}
+
+/// Reports where the access token comes from and whether one can be obtained, returning
+/// whether the credentials work.
+async fn check_credentials(report: &mut Report, config: &config::Config) -> bool {
+    let credentials = match auth::Credentials::discover(config) {
+        Ok(credentials) => credentials,
+        Err(e) => {
+            report.check(Status::Error, "credentials", e);
+            return false;
+        }
+    };
+    report.check(Status::Ok, "credentials", credentials.describe());
+
+    match credentials {
+        auth::Credentials::None if config.get_api_key().is_empty() => {
+            report.check(
+                Status::Error,
+                "api_key",
+                "not set, and no other credentials were found",
+            );
+            false
+        }
+        auth::Credentials::None => {
+            report.check(Status::Ok, "api_key", secrets::mask(&config.get_api_key()));
+            true
+        }
+        auth::Credentials::AccessToken(_) => {
+            // a fixed token carries no expiry, the backend check shows whether it still works
+            report.check(Status::Ok, "access token", "set, expiry unknown");
+            true
+        }
+        _ => match auth::access_token(config).await {
+            Ok(_) => {
+                let expiry = match auth::token_expires_in() {
+                    Some(secs) => format!("valid, expires in {} min", secs / 60),
+                    None => "valid".to_string(),
+                };
+                report.check(Status::Ok, "access token", expiry);
+                true
+            }
+            Err(e) => {
+                report.check(Status::Error, "access token", e);
+                false
+            }
+        },
+    }
+}
+
+/// Checks that files can be created in `dir` by creating and removing one.
+fn check_writable(dir: &Path) -> Result<(), String> {
+    if !dir.is_dir() {
+        return Err(format!("{} is not a directory", dir.display()));
+    }
+    let probe = dir.join(".pdf-translator-doctor");
+    fs::write(&probe, b"")
+        .and_then(|_| fs::remove_file(&probe))
+        .map_err(|e| format!("{} is not writable: {}", dir.display(), e))
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_check_writable() {
+        assert!(check_writable(&std::env::temp_dir()).is_ok());
+        assert!(check_writable(Path::new("/nonexistent/pdf-translator")).is_err());
+    }
+}
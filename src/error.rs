@@ -0,0 +1,352 @@
+//! The errors of the pipeline, with the file, page, segment or backend they happened in, and
+//! the exit codes they map to so scripts can tell the causes apart.
+
+use std::fmt;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// The result of the fallible functions of the pipeline.
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// Why translating a document failed.
+#[derive(Debug)]
+pub enum Error {
+    /// A file or argument that cannot be used, such as a missing file or an unknown language
+    Input(String),
+    /// The text of a pdf could not be extracted
+    Extract {
+        file: PathBuf,
+        page: Option<usize>,
+        message: String,
+    },
+    /// The pdf is encrypted and no password, or the wrong one, was given
+    Encrypted { file: PathBuf, password_given: bool },
+    /// The configuration is unreadable or lacks a required setting
+    Config(String),
+    /// The backend rejected the credentials, or no credentials could be obtained
+    Auth { backend: String, message: String },
+    /// The backend could not be reached
+    Network { backend: String, message: String },
+    /// The backend answered with an error or an unexpected response
+    Backend {
+        backend: String,
+        segment: Option<usize>,
+        message: String,
+    },
+    /// poppler is missing and could not be installed
+    Install(String),
+    /// Reading or writing a file failed
+    Io {
+        path: Option<PathBuf>,
+        source: io::Error,
+    },
+    /// An error while translating a file
+    InFile { file: PathBuf, source: Box<Error> },
+    /// The languages of a document that could not be translated, and whether the progress
+    /// of any of them was kept for `--resume`
+    Targets {
+        errors: Vec<(String, Error)>,
+        resumable: bool,
+    },
+    /// The files of a batch that could not be translated
+    Batch {
+        total: usize,
+        failures: Vec<(PathBuf, Error)>,
+    },
+    /// Any other failure
+    Other(String),
+}
+
+/// The exit code of a failure that has no more specific code, or of several different ones.
+pub const EXIT_FAILURE: i32 = 1;
+/// The exit code of a file or argument that cannot be used, the same code as a usage error.
+pub const EXIT_INPUT: i32 = 2;
+/// The exit code of a configuration problem.
+pub const EXIT_CONFIG: i32 = 3;
+/// The exit code of rejected or missing credentials.
+pub const EXIT_AUTH: i32 = 4;
+/// The exit code of a backend that could not be reached.
+pub const EXIT_NETWORK: i32 = 5;
+/// The exit code of an error returned by the backend.
+pub const EXIT_BACKEND: i32 = 6;
+/// The exit code of a missing poppler installation.
+pub const EXIT_INSTALL: i32 = 7;
+
+impl Error {
+    /// A failed request to `backend` that never got an answer.
+    pub fn network(backend: &str, error: impl fmt::Display) -> Error {
+        Error::Network {
+            backend: backend.to_string(),
+            message: error.to_string(),
+        }
+    }
+
+    /// An error answer or unexpected response from `backend`.
+    pub fn backend(backend: &str, message: impl Into<String>) -> Error {
+        Error::Backend {
+            backend: backend.to_string(),
+            segment: None,
+            message: message.into(),
+        }
+    }
+
+    /// Credentials that `backend` rejected or that could not be obtained for it.
+    pub fn auth(backend: &str, message: impl Into<String>) -> Error {
+        Error::Auth {
+            backend: backend.to_string(),
+            message: message.into(),
+        }
+    }
+
+    /// A failure to read or write `path`.
+    pub fn io(path: &Path, source: io::Error) -> Error {
+        Error::Io {
+            path: Some(path.to_path_buf()),
+            source,
+        }
+    }
+
+    /// Records the segment a backend error happened in.
+    pub fn at_segment(self, line: usize) -> Error {
+        match self {
+            Error::Backend {
+                backend,
+                segment: None,
+                message,
+            } => Error::Backend {
+                backend,
+                segment: Some(line),
+                message,
+            },
+            e => e,
+        }
+    }
+
+    /// Records the file an error happened in, unless the error already names it.
+    pub fn in_file(self, file: &Path) -> Error {
+        if matches!(
+            self,
+            Error::Extract { .. }
+                | Error::Encrypted { .. }
+                | Error::Io { path: Some(_), .. }
+                | Error::InFile { .. }
+        ) {
+            return self;
+        }
+        Error::InFile {
+            file: file.to_path_buf(),
+            source: Box::new(self),
+        }
+    }
+
+    /// The exit code of the process when it fails with this error.
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Error::Input(_) | Error::Extract { .. } | Error::Encrypted { .. } => EXIT_INPUT,
+            Error::Config(_) => EXIT_CONFIG,
+            Error::Auth { .. } => EXIT_AUTH,
+            Error::Network { .. } => EXIT_NETWORK,
+            Error::Backend { .. } => EXIT_BACKEND,
+            Error::Install(_) => EXIT_INSTALL,
+            Error::Io { .. } | Error::Other(_) => EXIT_FAILURE,
+            Error::InFile { source, .. } => source.exit_code(),
+            Error::Targets { errors, .. } => common_exit_code(errors.iter().map(|(_, e)| e)),
+            Error::Batch { failures, .. } => common_exit_code(failures.iter().map(|(_, e)| e)),
+        }
+    }
+}
+
+/// The exit code shared by all `errors`, or [`EXIT_FAILURE`] when they differ.
+fn common_exit_code<'a>(mut errors: impl Iterator<Item = &'a Error>) -> i32 {
+    let first = errors.next().map_or(EXIT_FAILURE, Error::exit_code);
+    if errors.all(|e| e.exit_code() == first) {
+        first
+    } else {
+        EXIT_FAILURE
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Input(message)
+            | Error::Config(message)
+            | Error::Install(message)
+            | Error::Other(message) => write!(f, "{}", message),
+            Error::Extract {
+                file,
+                page,
+                message,
+            } => {
+                write!(f, "Could not extract the text of '{}'", file.display())?;
+                if let Some(page) = page {
+                    write!(f, " on page {}", page)?;
+                }
+                write!(f, ": {}", message)
+            }
+            Error::Encrypted {
+                file,
+                password_given: false,
+            } => write!(
+                f,
+                "'{}' is encrypted, give its password with '--pdf-password'",
+                file.display()
+            ),
+            Error::Encrypted {
+                file,
+                password_given: true,
+            } => write!(f, "The password of '{}' is incorrect", file.display()),
+            Error::Auth { backend, message } => {
+                write!(f, "Authentication with {} failed: {}", backend, message)
+            }
+            Error::Network { backend, message } => {
+                write!(f, "Could not reach {}: {}", backend, message)
+            }
+            Error::Backend {
+                backend,
+                segment,
+                message,
+            } => {
+                write!(f, "{} failed", backend)?;
+                if let Some(segment) = segment {
+                    write!(f, " on segment {}", segment)?;
+                }
+                write!(f, ": {}", message)
+            }
+            Error::Io {
+                path: Some(path),
+                source,
+            } => write!(f, "'{}': {}", path.display(), source),
+            Error::Io { path: None, source } => write!(f, "{}", source),
+            Error::InFile { file, source } => write!(f, "'{}': {}", file.display(), source),
+            Error::Targets { errors, resumable } => {
+                let errors: Vec<String> = errors
+                    .iter()
+                    .map(|(target, e)| format!("'{}': {}", target, e))
+                    .collect();
+                write!(f, "{}", errors.join("; "))?;
+                if *resumable {
+                    write!(f, " (run again with '--resume' to continue)")?;
+                }
+                Ok(())
+            }
+            Error::Batch { total, failures } => {
+                write!(f, "{} of {} files failed", failures.len(), total)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Io { source, .. } => Some(source),
+            Error::InFile { source, .. } => Some(source.as_ref()),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(source: io::Error) -> Error {
+        Error::Io { path: None, source }
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Error {
+        Error::Other(message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Error {
+        Error::Other(message.to_string())
+    }
+}
+
+impl From<Box<dyn std::error::Error>> for Error {
+    fn from(e: Box<dyn std::error::Error>) -> Error {
+        match e.downcast::<Error>() {
+            Ok(e) => *e,
+            Err(e) => Error::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<Box<dyn std::error::Error + Send + Sync>> for Error {
+    fn from(e: Box<dyn std::error::Error + Send + Sync>) -> Error {
+        match e.downcast::<Error>() {
+            Ok(e) => *e,
+            Err(e) => Error::Other(e.to_string()),
+        }
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_exit_codes() {
+        assert_eq!(Error::Input("missing".into()).exit_code(), EXIT_INPUT);
+        assert_eq!(
+            Error::network("google-v2", "timed out").exit_code(),
+            EXIT_NETWORK
+        );
+        let in_file = Error::auth("google-v2", "API key not valid").in_file(Path::new("a.pdf"));
+        assert_eq!(in_file.exit_code(), EXIT_AUTH);
+        assert_eq!(
+            in_file.to_string(),
+            "'a.pdf': Authentication with google-v2 failed: API key not valid"
+        );
+
+        let batch = Error::Batch {
+            total: 3,
+            failures: vec![
+                (PathBuf::from("a.pdf"), Error::Input("a".into())),
+                (PathBuf::from("b.pdf"), Error::Input("b".into())),
+            ],
+        };
+        assert_eq!(batch.exit_code(), EXIT_INPUT);
+        let mixed = Error::Targets {
+            errors: vec![
+                ("sv".to_string(), Error::Input("a".into())),
+                ("de".to_string(), Error::backend("google-v2", "b")),
+            ],
+            resumable: false,
+        };
+        assert_eq!(mixed.exit_code(), EXIT_FAILURE);
+        assert_eq!(mixed.to_string(), "'sv': a; 'de': google-v2 failed: b");
+    }
+
+    #[test]
+    fn test_segment_context() {
+        let e = Error::backend("google-v3", "Glossary not found").at_segment(12);
+        assert_eq!(
+            e.to_string(),
+            "google-v3 failed on segment 12: Glossary not found"
+        );
+
+        let encrypted = Error::Encrypted {
+            file: PathBuf::from("report.pdf"),
+            password_given: false,
+        };
+        assert_eq!(
+            encrypted.in_file(Path::new("report.pdf")).to_string(),
+            "'report.pdf' is encrypted, give its password with '--pdf-password'"
+        );
+
+        let boxed: Box<dyn std::error::Error + Send + Sync> = Box::new(e);
+        assert_eq!(Error::from(boxed).exit_code(), EXIT_BACKEND);
+
+        let io = Error::io(
+            Path::new("out/a.txt"),
+            io::Error::new(io::ErrorKind::PermissionDenied, "permission denied"),
+        )
+        .in_file(Path::new("a.pdf"));
+        assert_eq!(io.exit_code(), EXIT_FAILURE);
+        assert_eq!(io.to_string(), "'out/a.txt': permission denied");
+        assert!(std::error::Error::source(&io).is_some());
+    }
+}
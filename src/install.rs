@@ -0,0 +1,298 @@
+//! The `install` module which provides functions to check if `poppler-utils` is installed and install it if it is not.
+//!
+//! The package manager is run directly, under `sudo` where it needs root, so `sudo` asks for
+//! the password on the terminal itself and the password never passes through this program.
+
+use crate::error::{self, Error};
+use std::process::Command;
+
+/// This function checks if `poppler-utils` is installed and installs it if it is not.
+///
+/// With `dry_run` the commands that would install it are printed instead of run.
+pub fn run(dry_run: bool) -> error::Result<()> {
+    if dry_run {
+        return install(true);
+    }
+
+    println!("Checking if poppler-utils is installed...");
+    if poppler_version().is_some() {
+        return Ok(());
+    }
+
+    println!("Poppler is not installed.");
+    install(false)?;
+
+    // the package manager can succeed without putting pdftotext on the PATH
+    match poppler_version() {
+        Some(version) => {
+            println!("Poppler installed successfully! ({})", version);
+            Ok(())
+        }
+        None => Err(Error::Install(
+            "The installation finished but 'pdftotext -v' still fails, check that poppler is on the PATH".to_string(),
+        )),
+    }
+}
+
+/// Runs `program` with `args`, waiting for it to finish and failing if it does not succeed,
+/// or only prints the command with `dry_run`.
+fn run_command(program: &str, args: &[&str], dry_run: bool) -> error::Result<()> {
+    if dry_run {
+        println!("{} {}", program, args.join(" "));
+        return Ok(());
+    }
+
+    println!("Running: {} {}", program, args.join(" "));
+    let status = Command::new(program)
+        .args(args)
+        .status()
+        .map_err(|e| Error::Install(format!("Could not run '{}': {}", program, e)))?;
+
+    if status.success() {
+        Ok(())
+    } else {
+        Err(Error::Install(format!(
+            "'{} {}' failed with {}",
+            program,
+            args.join(" "),
+            status
+        )))
+    }
+}
+
+/// A Linux package manager and how poppler is installed with it.
+#[cfg(target_os = "linux")]
+#[derive(Debug, PartialEq)]
+struct PackageManager {
+    /// The program that is looked up on the PATH to find the package manager
+    program: &'static str,
+    /// The arguments that install poppler, including the package name
+    install_args: &'static [&'static str],
+    /// Whether installing needs root
+    needs_root: bool,
+}
+
+/// The package managers in the order they are looked for, as some systems have several.
+#[cfg(target_os = "linux")]
+const PACKAGE_MANAGERS: &[PackageManager] = &[
+    PackageManager {
+        program: "apt-get",
+        install_args: &["install", "-y", "poppler-utils"],
+        needs_root: true,
+    },
+    PackageManager {
+        program: "dnf",
+        install_args: &["install", "-y", "poppler-utils"],
+        needs_root: true,
+    },
+    PackageManager {
+        program: "yum",
+        install_args: &["install", "-y", "poppler-utils"],
+        needs_root: true,
+    },
+    PackageManager {
+        program: "zypper",
+        install_args: &["--non-interactive", "install", "poppler-tools"],
+        needs_root: true,
+    },
+    PackageManager {
+        program: "pacman",
+        install_args: &["-S", "--noconfirm", "poppler"],
+        needs_root: true,
+    },
+    PackageManager {
+        program: "apk",
+        install_args: &["add", "poppler-utils"],
+        needs_root: true,
+    },
+    // nix installs into the user's profile
+    PackageManager {
+        program: "nix-env",
+        install_args: &["-iA", "nixpkgs.poppler_utils"],
+        needs_root: false,
+    },
+];
+
+#[cfg(target_os = "linux")]
+impl PackageManager {
+    /// The program and arguments that install poppler, run with sudo unless `root` already.
+    fn install_command(&self, root: bool) -> (&'static str, Vec<&'static str>) {
+        if self.needs_root && !root {
+            let mut args = vec![self.program];
+            args.extend(self.install_args);
+            ("sudo", args)
+        } else {
+            (self.program, self.install_args.to_vec())
+        }
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn install(dry_run: bool) -> error::Result<()> {
+    let installed_manager = get_package_manager().ok_or_else(|| {
+        let known: Vec<_> = PACKAGE_MANAGERS.iter().map(|m| m.program).collect();
+        Error::Install(format!(
+            "No supported package manager was found ({}), install poppler manually",
+            known.join(", ")
+        ))
+    })?;
+
+    // sudo prompts for the password on the terminal when it needs one
+    let (program, args) = installed_manager.install_command(is_root());
+    run_command(program, &args, dry_run)
+}
+
+#[cfg(target_os = "linux")]
+fn get_package_manager() -> Option<&'static PackageManager> {
+    PACKAGE_MANAGERS.iter().find(|manager| {
+        // If the command succeeded, then the package manager exists on the system
+        Command::new("which")
+            .arg(manager.program)
+            .output()
+            .is_ok_and(|output| output.status.success())
+    })
+}
+
+/// Whether the program runs as root, where sudo is not needed and often not installed.
+#[cfg(target_os = "linux")]
+fn is_root() -> bool {
+    Command::new("id")
+        .arg("-u")
+        .output()
+        .is_ok_and(|output| String::from_utf8_lossy(&output.stdout).trim() == "0")
+}
+
+#[cfg(target_os = "macos")]
+const BREW_INSTALL_SCRIPT: &str =
+    "https://raw.githubusercontent.com/Homebrew/install/HEAD/install.sh";
+
+#[cfg(target_os = "macos")]
+fn install(dry_run: bool) -> error::Result<()> {
+    if dry_run && !check_brew() {
+        println!("/bin/bash -c \"$(curl -fsSL {})\"", BREW_INSTALL_SCRIPT);
+    } else if !check_brew() {
+        // the Homebrew installer asks for the password itself when it needs one
+        let script = Command::new("curl")
+            .args(["-fsSL", BREW_INSTALL_SCRIPT])
+            .output()
+            .map_err(|e| Error::Install(format!("Could not download Homebrew: {}", e)))?;
+        if !script.status.success() {
+            return Err(Error::Install(format!(
+                "Could not download Homebrew from {}",
+                BREW_INSTALL_SCRIPT
+            )));
+        }
+        run_command(
+            "/bin/bash",
+            &["-c", &String::from_utf8_lossy(&script.stdout)],
+            false,
+        )?;
+    }
+
+    // Homebrew refuses to run as root, so it is not run with sudo
+    run_command("brew", &["install", "poppler"], dry_run)
+}
+
+#[cfg(target_os = "macos")]
+fn check_brew() -> bool {
+    let output = match Command::new("which").arg("brew").output() {
+        Ok(output) => output,
+        Err(_) => return false,
+    };
+
+    // If the command succeeded, then brew exists on the system
+    output.status.success()
+}
+
+#[cfg(target_os = "windows")]
+fn install(dry_run: bool) -> error::Result<()> {
+    // Check if Chocolaty is installed, if not then install it
+    if !check_chocolaty() {
+        run_command("powershell", &["-Command", "Set-ExecutionPolicy Bypass -Scope Process -Force; [System.Net.ServicePointManager]::SecurityProtocol = [System.Net.ServicePointManager]::SecurityProtocol -bor 3072; iex ((New-Object System.Net.WebClient).DownloadString('https://chocolatey.org/install.ps1'))"], dry_run)?;
+    }
+
+    // Install poppler-utils using Chocolaty
+    run_command("choco", &["install", "poppler", "-y"], dry_run)
+}
+
+#[cfg(target_os = "windows")]
+fn check_chocolaty() -> bool {
+    // If the command succeeded, then Chocolaty exists on the system
+    Command::new("where")
+        .arg("choco")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+/// The version line printed by `pdftotext -v`, if poppler's `pdftotext` can be run.
+pub fn poppler_version() -> Option<String> {
+    tool_version("pdftotext")
+}
+
+/// The version line printed by `<tool> -v`, if the poppler tool can be run.
+pub fn tool_version(tool: &str) -> Option<String> {
+    let output = Command::new(tool).arg("-v").output().ok()?;
+    // the poppler tools print their version to stderr
+    let text = String::from_utf8_lossy(&output.stderr);
+    if !text.contains("Poppler") {
+        return None;
+    }
+    text.lines().next().map(|line| line.trim().to_string())
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_linux_package_manager_check() {
+        let result = get_package_manager();
+        assert!(result.is_some());
+    }
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_install_commands() {
+        let pacman = PACKAGE_MANAGERS
+            .iter()
+            .find(|m| m.program == "pacman")
+            .unwrap();
+        assert_eq!(
+            pacman.install_command(false),
+            ("sudo", vec!["pacman", "-S", "--noconfirm", "poppler"])
+        );
+        assert_eq!(
+            pacman.install_command(true),
+            ("pacman", vec!["-S", "--noconfirm", "poppler"])
+        );
+
+        let nix = PACKAGE_MANAGERS.last().unwrap();
+        assert_eq!(nix.install_command(false).0, "nix-env");
+    }
+
+    #[cfg(target_os = "macos")]
+    #[test]
+    fn test_macos_brew_check() {
+        let result = check_brew();
+        assert!(result);
+    }
+
+    #[cfg(target_os = "windows")]
+    #[test]
+    fn test_windows_chocolaty_check() {
+        let result = check_chocolaty();
+        assert!(result);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_run_command_reports_failure() {
+        assert!(run_command("true", &[], false).is_ok());
+        assert!(run_command("false", &[], true).is_ok());
+        assert_eq!(
+            run_command("false", &[], false).unwrap_err().exit_code(),
+            error::EXIT_INSTALL
+        );
+    }
+}
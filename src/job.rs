@@ -0,0 +1,184 @@
+//! The `job` module which records every translated line as soon as it is done, so that an
+//! interrupted translation can be continued with `--resume` instead of starting over.
+
+use crate::cache;
+use crate::status;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+/// The first line of a manifest, identifying the job it belongs to.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+struct Header {
+    input_hash: String,
+    source: String,
+    target: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Entry {
+    line: usize,
+    text: String,
+}
+
+/// A job manifest stored as JSON lines: a header followed by one entry per translated line.
+/// Entries are appended as they finish, so a crash loses at most the line in flight.
+#[derive(Debug, Clone)]
+pub struct JobManifest {
+    path: PathBuf,
+    completed: BTreeMap<usize, String>,
+}
+
+impl JobManifest {
+    /// Opens the manifest for `input_hash` and the language pair.
+    ///
+    /// With `resume` the lines completed by an earlier run are loaded, otherwise any earlier
+    /// manifest is discarded and the job starts from the beginning.
+    pub fn open(
+        input_hash: &str,
+        source: &str,
+        target: &str,
+        resume: bool,
+    ) -> Result<JobManifest, Box<dyn std::error::Error + Send + Sync>> {
+        let path = Self::get_jobs_dir()?.join(Self::file_name(input_hash, source, target));
+        Self::open_at(path, input_hash, source, target, resume)
+    }
+
+    /// Whether a manifest for `input_hash` and the language pair is left from a job that did
+    /// not finish, so `--resume` can continue it.
+    pub fn exists(input_hash: &str, source: &str, target: &str) -> bool {
+        Self::get_jobs_dir()
+            .map(|dir| {
+                dir.join(Self::file_name(input_hash, source, target))
+                    .exists()
+            })
+            .unwrap_or(false)
+    }
+
+    fn file_name(input_hash: &str, source: &str, target: &str) -> String {
+        format!("{}-{}-{}.jsonl", input_hash, source, target)
+    }
+
+    /// Opens the manifest at `path` instead of in the cache directory.
+    pub fn open_at(
+        path: PathBuf,
+        input_hash: &str,
+        source: &str,
+        target: &str,
+        resume: bool,
+    ) -> Result<JobManifest, Box<dyn std::error::Error + Send + Sync>> {
+        let header = Header {
+            input_hash: input_hash.to_string(),
+            source: source.to_string(),
+            target: target.to_string(),
+        };
+
+        let mut completed = BTreeMap::new();
+        if resume {
+            match fs::read_to_string(&path) {
+                Ok(contents) => completed = Self::parse(&contents, &header),
+                Err(_) => status!("No interrupted job found, starting from the beginning"),
+            }
+        }
+
+        if completed.is_empty() {
+            fs::write(&path, serde_json::to_string(&header)? + "\n")?;
+        } else {
+            status!("Resuming job, {} lines already translated", completed.len());
+        }
+
+        Ok(JobManifest { path, completed })
+    }
+
+    /// Reads the completed entries of a manifest, ignoring it if it belongs to another job
+    /// and skipping a last entry that was cut off while being written.
+    fn parse(contents: &str, header: &Header) -> BTreeMap<usize, String> {
+        let mut lines = contents.lines();
+        let matches = lines
+            .next()
+            .and_then(|line| serde_json::from_str::<Header>(line).ok())
+            .map(|found| &found == header)
+            .unwrap_or(false);
+
+        if !matches {
+            return BTreeMap::new();
+        }
+
+        lines
+            .filter_map(|line| serde_json::from_str::<Entry>(line).ok())
+            .map(|entry| (entry.line, entry.text))
+            .collect()
+    }
+
+    /// The translation of `line` from an earlier run, if there is one.
+    pub fn get(&self, line: usize) -> Option<&String> {
+        self.completed.get(&line)
+    }
+
+    /// Records a translated line and appends it to the manifest on disk.
+    pub fn complete(
+        &mut self,
+        line: usize,
+        text: &str,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let entry = Entry {
+            line,
+            text: text.to_string(),
+        };
+        let mut file = OpenOptions::new().append(true).open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(&entry)?)?;
+        self.completed.insert(line, entry.text);
+        Ok(())
+    }
+
+    /// Removes the manifest once the job has finished.
+    pub fn finish(self) -> std::io::Result<()> {
+        fs::remove_file(&self.path)
+    }
+
+    fn get_jobs_dir() -> Result<PathBuf, Box<dyn std::error::Error + Send + Sync>> {
+        let jobs_dir = cache::get_cache_dir()?.join("jobs");
+        if !jobs_dir.exists() {
+            fs::create_dir_all(&jobs_dir)?;
+        }
+        Ok(jobs_dir)
+    }
+}
+
+/// The SHA-256 of the file at `path` as a hex string, identifying the input of a job.
+pub fn hash_file(path: &Path) -> std::io::Result<String> {
+    let bytes = fs::read(path)?;
+    Ok(format!("{:x}", Sha256::digest(bytes)))
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_resume_manifest() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("job.jsonl");
+
+        let mut job = JobManifest::open_at(path.clone(), "abc", "en", "sv", false).unwrap();
+        job.complete(0, "Hej").unwrap();
+        job.complete(2, "Världen").unwrap();
+        // simulate a crash in the middle of writing an entry
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        write!(file, "{{\"line\":3,\"te").unwrap();
+
+        let resumed = JobManifest::open_at(path.clone(), "abc", "en", "sv", true).unwrap();
+        assert_eq!(resumed.get(0), Some(&"Hej".to_string()));
+        assert_eq!(resumed.get(2), Some(&"Världen".to_string()));
+        assert_eq!(resumed.get(3), None);
+
+        // a manifest for another language pair is not reused
+        let other = JobManifest::open_at(path.clone(), "abc", "en", "de", true).unwrap();
+        assert_eq!(other.get(0), None);
+        other.finish().unwrap();
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,448 @@
+//! The `languages` module which holds the languages supported by the translation backend and
+//! resolves the codes, names and aliases given on the command line.
+
+use crate::cache;
+use crate::status;
+use crate::translator;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long a language list fetched from the backend is used before fetching it again.
+const CACHE_MAX_AGE: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Where a list of languages came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LanguageSource {
+    Backend,
+    Cache,
+    BuiltIn,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CachedLanguages {
+    fetched_at: u64,
+    languages: Vec<(String, String)>,
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|duration| duration.as_secs())
+        .unwrap_or_default()
+}
+
+fn read_cached() -> Option<CachedLanguages> {
+    let path = cache::get_cache_dir().ok()?.join(format!(
+        "languages-{}.json",
+        translator::active_backend().name()
+    ));
+    serde_json::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+fn write_cached(languages: &[(String, String)]) -> Result<(), Box<dyn std::error::Error>> {
+    let path = cache::get_cache_dir()?.join(format!(
+        "languages-{}.json",
+        translator::active_backend().name()
+    ));
+    let cached = CachedLanguages {
+        fetched_at: now(),
+        languages: languages.to_vec(),
+    };
+    fs::write(path, serde_json::to_string(&cached)?)?;
+    Ok(())
+}
+
+/// The languages last fetched from the active backend as `(code, name)` pairs, however old
+/// they are, without reaching the backend.
+pub fn cached() -> Option<Vec<(String, String)>> {
+    read_cached().map(|cached| cached.languages)
+}
+
+/// The built-in table as `(code, name)` pairs, with the aliases listed next to the code.
+fn built_in() -> Vec<(String, String)> {
+    SUPPORTED_LANGUAGES
+        .iter()
+        .map(|(name, code, aliases)| {
+            let code = if aliases.is_empty() {
+                code.to_string()
+            } else {
+                format!("{} (also: {})", code, aliases.join(", "))
+            };
+            (code, name.to_string())
+        })
+        .collect()
+}
+
+/// The languages supported by the active backend as `(code, name)` pairs.
+///
+/// A list fetched within the last week is reused unless `refresh` is set. If the backend
+/// cannot be reached an older cached list is used, and without one the built-in table.
+pub async fn available(refresh: bool) -> (Vec<(String, String)>, LanguageSource) {
+    let cached = read_cached();
+    if !refresh {
+        if let Some(cached) = &cached {
+            if now().saturating_sub(cached.fetched_at) < CACHE_MAX_AGE.as_secs() {
+                return (cached.languages.clone(), LanguageSource::Cache);
+            }
+        }
+    }
+
+    match translator::fetch_languages().await {
+        Ok(languages) => {
+            if let Err(e) = write_cached(&languages) {
+                status!("Error caching the supported languages: {}", e);
+            }
+            (languages, LanguageSource::Backend)
+        }
+        Err(e) => {
+            status!("Could not fetch the supported languages: {}", e);
+            match cached {
+                Some(cached) => (cached.languages, LanguageSource::Cache),
+                None => (built_in(), LanguageSource::BuiltIn),
+            }
+        }
+    }
+}
+
+/// The supported languages as `(name, code, aliases)`, where the aliases are other codes or
+/// names the backend or users know the language by.
+pub static SUPPORTED_LANGUAGES: &[(&str, &str, &[&str])] = &[
+    ("Afrikaans", "af", &[]),
+    ("Albanian", "sq", &[]),
+    ("Amharic", "am", &[]),
+    ("Arabic", "ar", &[]),
+    ("Armenian", "hy", &[]),
+    ("Assamese", "as", &[]),
+    ("Aymara", "ay", &[]),
+    ("Azerbaijani", "az", &[]),
+    ("Bambara", "bm", &[]),
+    ("Basque", "eu", &[]),
+    ("Belarusian", "be", &[]),
+    ("Bengali", "bn", &[]),
+    ("Bhojpuri", "bho", &[]),
+    ("Bosnian", "bs", &[]),
+    ("Bulgarian", "bg", &[]),
+    ("Catalan", "ca", &[]),
+    ("Cebuano", "ceb", &[]),
+    ("Chinese (Simplified)", "zh-CN", &["zh"]),
+    ("Chinese (Traditional)", "zh-TW", &[]),
+    ("Corsican", "co", &[]),
+    ("Czech", "cs", &[]),
+    ("Danish", "da", &[]),
+    ("Dhivehi", "dv", &[]),
+    ("Dogri", "doi", &[]),
+    ("Dutch", "nl", &[]),
+    ("English", "en", &[]),
+    ("Esperanto", "eo", &[]),
+    ("Estonian", "et", &[]),
+    ("Ewe", "ee", &[]),
+    ("Filipino (Tagalog)", "fil", &[]),
+    ("Finnish", "fi", &[]),
+    ("French", "fr", &[]),
+    ("Frisian", "fy", &[]),
+    ("Galician", "gl", &[]),
+    ("Georgian", "ka", &[]),
+    ("German", "de", &[]),
+    ("Greek", "el", &[]),
+    ("Guarani", "gn", &[]),
+    ("Gujarati", "gu", &[]),
+    ("Haitian Creole", "ht", &["Haitian"]),
+    ("Hausa", "ha", &[]),
+    ("Hawaiian", "haw", &[]),
+    ("Hebrew", "he", &["iw"]),
+    ("Hindi", "hi", &[]),
+    ("Hmong", "hmn", &[]),
+    ("Hungarian", "hu", &[]),
+    ("Icelandic", "is", &[]),
+    ("Igbo", "ig", &[]),
+    ("Ilocano", "ilo", &[]),
+    ("Indonesian", "id", &[]),
+    ("Irish", "ga", &[]),
+    ("Italian", "it", &[]),
+    ("Japanese", "ja", &[]),
+    ("Javanese", "jv", &["jw"]),
+    ("Kannada", "kn", &[]),
+    ("Kazakh", "kk", &[]),
+    ("Khmer", "km", &[]),
+    ("Kinyarwanda", "rw", &[]),
+    ("Konkani", "gom", &[]),
+    ("Korean", "ko", &[]),
+    ("Krio", "kri", &[]),
+    ("Kurdish", "ku", &[]),
+    ("Kurdish (Sorani)", "ckb", &[]),
+    ("Kyrgyz", "ky", &[]),
+    ("Lao", "lo", &[]),
+    ("Latin", "la", &[]),
+    ("Latvian", "lv", &[]),
+    ("Lingala", "ln", &[]),
+    ("Lithuanian", "lt", &[]),
+    ("Luganda", "lg", &[]),
+    ("Luxembourgish", "lb", &[]),
+    ("Macedonian", "mk", &[]),
+    ("Maithili", "mai", &[]),
+    ("Malagasy", "mg", &[]),
+    ("Malay", "ms", &[]),
+    ("Malayalam", "ml", &[]),
+    ("Maltese", "mt", &[]),
+    ("Maori", "mi", &[]),
+    ("Marathi", "mr", &[]),
+    ("Meiteilon (Manipuri)", "mni-Mtei", &[]),
+    ("Mizo", "lus", &[]),
+    ("Mongolian", "mn", &[]),
+    ("Myanmar (Burmese)", "my", &["Burmese"]),
+    ("Nepali", "ne", &[]),
+    ("Norwegian", "no", &[]),
+    ("Nyanja (Chichewa)", "ny", &[]),
+    ("Odia (Oriya)", "or", &[]),
+    ("Oromo", "om", &[]),
+    ("Pashto", "ps", &[]),
+    ("Persian", "fa", &["Farsi"]),
+    ("Polish", "pl", &[]),
+    ("Portuguese (Portugal, Brazil)", "pt", &[]),
+    ("Punjabi", "pa", &[]),
+    ("Quechua", "qu", &[]),
+    ("Romanian", "ro", &[]),
+    ("Russian", "ru", &[]),
+    ("Samoan", "sm", &[]),
+    ("Sanskrit", "sa", &[]),
+    ("Scots Gaelic", "gd", &["Gaelic"]),
+    ("Sepedi", "nso", &[]),
+    ("Serbian", "sr", &[]),
+    ("Sesotho", "st", &[]),
+    ("Shona", "sn", &[]),
+    ("Sindhi", "sd", &[]),
+    ("Sinhala (Sinhalese)", "si", &[]),
+    ("Slovak", "sk", &[]),
+    ("Slovenian", "sl", &[]),
+    ("Somali", "so", &[]),
+    ("Spanish", "es", &[]),
+    ("Sundanese", "su", &[]),
+    ("Swahili", "sw", &[]),
+    ("Swedish", "sv", &[]),
+    ("Tagalog (Filipino)", "tl", &[]),
+    ("Tajik", "tg", &[]),
+    ("Tamil", "ta", &[]),
+    ("Tatar", "tt", &[]),
+    ("Telugu", "te", &[]),
+    ("Thai", "th", &[]),
+    ("Tigrinya", "ti", &[]),
+    ("Tsonga", "ts", &[]),
+    ("Turkish", "tr", &[]),
+    ("Turkmen", "tk", &[]),
+    ("Twi (Akan)", "ak", &[]),
+    ("Ukrainian", "uk", &[]),
+    ("Urdu", "ur", &[]),
+    ("Uyghur", "ug", &[]),
+    ("Uzbek", "uz", &[]),
+    ("Vietnamese", "vi", &[]),
+    ("Welsh", "cy", &[]),
+    ("Xhosa", "xh", &[]),
+    ("Yiddish", "yi", &[]),
+    ("Yoruba", "yo", &[]),
+    ("Zulu", "zu", &[]),
+];
+
+/// The part of a name before any parenthesis, e.g. `Myanmar` for `Myanmar (Burmese)`.
+fn base_name(name: &str) -> &str {
+    name.split(" (").next().unwrap_or(name)
+}
+
+/// Resolves a language code, alias or name (case-insensitive) to the code sent to the
+/// backend, suggesting the closest supported language when nothing matches.
+pub fn resolve(input: &str) -> Result<&'static str, String> {
+    let wanted = input.trim();
+    let matches = |candidate: &str| candidate.eq_ignore_ascii_case(wanted);
+
+    let found = SUPPORTED_LANGUAGES
+        .iter()
+        .find(|(_, code, _)| matches(code))
+        .or_else(|| {
+            SUPPORTED_LANGUAGES
+                .iter()
+                .find(|(_, _, aliases)| aliases.iter().any(|alias| matches(alias)))
+        })
+        .or_else(|| {
+            SUPPORTED_LANGUAGES
+                .iter()
+                .find(|(name, _, _)| matches(name))
+        })
+        .or_else(|| {
+            SUPPORTED_LANGUAGES
+                .iter()
+                .find(|(name, _, _)| matches(base_name(name)))
+        });
+
+    match found {
+        Some((_, code, _)) => Ok(code),
+        None => match suggest(wanted) {
+            Some((name, code)) => Err(format!(
+                "Unknown language '{}', did you mean '{}' ({})?",
+                input, name, code
+            )),
+            None => Err(format!(
+                "Unknown language '{}', use 'pdf-translator languages' to see the supported languages",
+                input
+            )),
+        },
+    }
+}
+
+/// Resolves a language like [`resolve`], then checks it against `supported`, the `(code, name)`
+/// pairs fetched from the backend, when there is such a list.
+///
+/// A code or name the backend lists is accepted even if the built-in table does not know it,
+/// and a language from the table is rejected when the backend lists neither its code nor
+/// one of its aliases.
+pub fn resolve_supported(
+    input: &str,
+    supported: Option<&[(String, String)]>,
+) -> Result<String, String> {
+    let Some(supported) = supported else {
+        return resolve(input).map(str::to_string);
+    };
+    let listed = |candidate: &str| {
+        supported
+            .iter()
+            .find(|(code, _)| code.eq_ignore_ascii_case(candidate))
+            .map(|(code, _)| code.clone())
+    };
+
+    match resolve(input) {
+        Ok(code) => {
+            let aliases = SUPPORTED_LANGUAGES
+                .iter()
+                .find(|(_, known, _)| *known == code)
+                .map_or(&[][..], |(_, _, aliases)| aliases);
+            std::iter::once(code)
+                .chain(aliases.iter().copied())
+                .find_map(listed)
+                .ok_or_else(|| {
+                    format!(
+                        "Language '{}' ({}) is not supported by the {} backend",
+                        input,
+                        code,
+                        translator::active_backend().name()
+                    )
+                })
+        }
+        Err(e) => {
+            let wanted = input.trim();
+            listed(wanted)
+                .or_else(|| {
+                    supported
+                        .iter()
+                        .find(|(_, name)| name.eq_ignore_ascii_case(wanted))
+                        .map(|(code, _)| code.clone())
+                })
+                .ok_or(e)
+        }
+    }
+}
+
+/// The supported language closest to `input` by edit distance, if any is close enough to
+/// be a likely typo.
+fn suggest(input: &str) -> Option<(&'static str, &'static str)> {
+    let input = &input.to_lowercase();
+    let max_distance = (input.chars().count() / 3).max(1);
+
+    SUPPORTED_LANGUAGES
+        .iter()
+        .flat_map(|&(name, code, aliases)| {
+            [name, base_name(name), code]
+                .into_iter()
+                .chain(aliases.iter().copied())
+                .map(move |candidate| (levenshtein(input, &candidate.to_lowercase()), name, code))
+        })
+        .filter(|(distance, _, _)| *distance <= max_distance)
+        .min_by_key(|(distance, _, _)| *distance)
+        .map(|(_, name, code)| (name, code))
+}
+
+fn levenshtein(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a_char) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b_char) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a_char != *b_char);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+
+    previous[b.len()]
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_resolve_codes_names_and_aliases() {
+        assert_eq!(resolve("sv"), Ok("sv"));
+        assert_eq!(resolve("ZH"), Ok("zh-CN"));
+        assert_eq!(resolve("iw"), Ok("he"));
+        assert_eq!(resolve("swedish"), Ok("sv"));
+        assert_eq!(resolve("Burmese"), Ok("my"));
+        assert_eq!(resolve("Kurdish"), Ok("ku"));
+    }
+
+    #[test]
+    fn test_suggest_on_typo() {
+        assert_eq!(
+            resolve("Sweedish"),
+            Err("Unknown language 'Sweedish', did you mean 'Swedish' (sv)?".to_string())
+        );
+        assert!(resolve("xx-unknown")
+            .unwrap_err()
+            .contains("pdf-translator languages"));
+    }
+
+    #[test]
+    fn test_resolve_supported_by_backend() {
+        let supported: Vec<(String, String)> =
+            [("sv", "Swedish"), ("iw", "Hebrew"), ("xx", "Klingon")]
+                .iter()
+                .map(|(code, name)| (code.to_string(), name.to_string()))
+                .collect();
+        let supported = Some(supported.as_slice());
+
+        assert_eq!(
+            resolve_supported("Swedish", supported),
+            Ok("sv".to_string())
+        );
+        assert_eq!(resolve_supported("he", supported), Ok("iw".to_string()));
+        assert_eq!(resolve_supported("XX", supported), Ok("xx".to_string()));
+        assert_eq!(
+            resolve_supported("klingon", supported),
+            Ok("xx".to_string())
+        );
+        assert!(resolve_supported("de", supported)
+            .unwrap_err()
+            .contains("not supported by the"));
+        assert!(resolve_supported("Sweedish", None)
+            .unwrap_err()
+            .contains("did you mean 'Swedish'"));
+        assert_eq!(resolve_supported("de", None), Ok("de".to_string()));
+    }
+
+    #[test]
+    fn test_built_in_lists_aliases() {
+        let languages = built_in();
+        assert_eq!(languages.len(), SUPPORTED_LANGUAGES.len());
+        assert!(languages.contains(&("he (also: iw)".to_string(), "Hebrew".to_string())));
+    }
+
+    #[test]
+    fn test_no_duplicate_languages() {
+        let mut names: Vec<&str> = SUPPORTED_LANGUAGES
+            .iter()
+            .map(|(name, _, _)| *name)
+            .collect();
+        names.sort();
+        names.dedup();
+        assert_eq!(names.len(), SUPPORTED_LANGUAGES.len());
+    }
+}
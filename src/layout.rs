@@ -0,0 +1,457 @@
+//! The positions of the text on every page, read from the XHTML that `pdftotext -bbox-layout`
+//! writes: pages hold blocks of lines of words, each with its bounding box in points. The
+//! font sizes are not in that output, they are taken from the runs of text `pdftohtml -xml`
+//! writes and given to the words the runs cover.
+//!
+//! The layout is only available through the library, for writers that place the translation
+//! where the text was. The `translate` command still translates the text of `pdftotext
+//! -layout` and its JSON output holds no positions, since it writes no format that could use
+//! them.
+
+use crate::xml::{self, Token};
+use std::collections::BTreeMap;
+
+/// A rectangle on the page in points, with the origin in the top left corner.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct BBox {
+    pub x_min: f64,
+    pub y_min: f64,
+    pub x_max: f64,
+    pub y_max: f64,
+}
+
+impl BBox {
+    /// The width of the box.
+    pub fn width(&self) -> f64 {
+        self.x_max - self.x_min
+    }
+
+    /// The height of the box.
+    pub fn height(&self) -> f64 {
+        self.y_max - self.y_min
+    }
+
+    fn from_attributes(attributes: &BTreeMap<String, String>) -> BBox {
+        let get = |name: &str| {
+            attributes
+                .get(name)
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_default()
+        };
+        BBox {
+            x_min: get("xMin"),
+            y_min: get("yMin"),
+            x_max: get("xMax"),
+            y_max: get("yMax"),
+        }
+    }
+}
+
+/// A single word and where it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Word {
+    pub bbox: BBox,
+    pub text: String,
+    /// The font size in points, rounded to whole points by pdftohtml, if a run of text it
+    /// wrote covers the word
+    pub font_size: Option<f64>,
+}
+
+impl Word {
+    /// The height of the word in points, which is usually somewhat larger than the font
+    /// size.
+    pub fn height(&self) -> f64 {
+        self.bbox.height()
+    }
+}
+
+/// The most common of `values` to a tenth, so rounding noise does not split them.
+fn most_common(values: impl Iterator<Item = f64>) -> Option<f64> {
+    let mut counts: BTreeMap<u64, usize> = BTreeMap::new();
+    for value in values {
+        *counts.entry((value * 10.0).round() as u64).or_default() += 1;
+    }
+    counts
+        .into_iter()
+        .max_by_key(|&(_, count)| count)
+        .map(|(value, _)| value as f64 / 10.0)
+}
+
+/// A line of words in a block.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Line {
+    pub bbox: BBox,
+    pub words: Vec<Word>,
+}
+
+impl Line {
+    /// The words of the line separated by spaces.
+    pub fn text(&self) -> String {
+        let words: Vec<&str> = self.words.iter().map(|word| word.text.as_str()).collect();
+        words.join(" ")
+    }
+
+    /// The most common height of the words in the line, which unlike the height of the line
+    /// is not raised by a single taller word.
+    pub fn word_height(&self) -> f64 {
+        most_common(self.words.iter().map(Word::height)).unwrap_or_default()
+    }
+
+    /// The most common font size of the words in the line, if any of them has one.
+    pub fn font_size(&self) -> Option<f64> {
+        most_common(self.words.iter().filter_map(|word| word.font_size))
+    }
+}
+
+/// A paragraph-like group of lines.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Block {
+    pub bbox: BBox,
+    pub lines: Vec<Line>,
+}
+
+impl Block {
+    /// The lines of the block separated by newlines.
+    pub fn text(&self) -> String {
+        let lines: Vec<String> = self.lines.iter().map(Line::text).collect();
+        lines.join("\n")
+    }
+}
+
+/// A page and the blocks on it, in the reading order pdftotext found.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Page {
+    /// The page number, starting at 1
+    pub number: usize,
+    pub width: f64,
+    pub height: f64,
+    pub blocks: Vec<Block>,
+}
+
+impl Page {
+    /// Groups the blocks into columns, left to right, with the blocks of every column from
+    /// top to bottom.
+    ///
+    /// Blocks that overlap horizontally are in the same column. Blocks wider than half the
+    /// page, such as a title above two columns, would join every column, so they do not
+    /// shape the columns and are put in the first column they overlap.
+    pub fn columns(&self) -> Vec<Vec<&Block>> {
+        let is_wide = |block: &Block| block.bbox.width() > self.width / 2.0;
+
+        let mut spans: Vec<(f64, f64)> = self
+            .blocks
+            .iter()
+            .filter(|block| !is_wide(block))
+            .map(|block| (block.bbox.x_min, block.bbox.x_max))
+            .collect();
+        spans.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut columns: Vec<(f64, f64)> = Vec::new();
+        for (start, end) in spans {
+            match columns.last_mut() {
+                Some(column) if start <= column.1 => column.1 = column.1.max(end),
+                _ => columns.push((start, end)),
+            }
+        }
+        if columns.is_empty() {
+            columns.push((0.0, self.width));
+        }
+
+        let mut grouped: Vec<Vec<&Block>> = vec![Vec::new(); columns.len()];
+        for block in &self.blocks {
+            let column = columns
+                .iter()
+                .position(|&(start, end)| block.bbox.x_min < end && block.bbox.x_max > start)
+                .unwrap_or(0);
+            grouped[column].push(block);
+        }
+        for column in &mut grouped {
+            column.sort_by(|a, b| a.bbox.y_min.total_cmp(&b.bbox.y_min));
+        }
+        grouped
+    }
+}
+
+/// The text of a pdf with its layout.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Document {
+    pub pages: Vec<Page>,
+}
+
+/// Parses the output of `pdftotext -bbox-layout` into a document.
+///
+/// Elements other than pages, blocks, lines and words are skipped, and flows are flattened
+/// into the blocks of their page.
+pub fn parse(xml: &str) -> Document {
+    let mut document = Document::default();
+    let mut word: Option<Word> = None;
+
+    for token in xml::tokenize(xml) {
+        match token {
+            Token::Start(name, attributes) => match name.as_str() {
+                "page" => {
+                    let get = |name: &str| {
+                        attributes
+                            .get(name)
+                            .and_then(|value| value.parse().ok())
+                            .unwrap_or_default()
+                    };
+                    document.pages.push(Page {
+                        number: document.pages.len() + 1,
+                        width: get("width"),
+                        height: get("height"),
+                        blocks: Vec::new(),
+                    });
+                }
+                "block" => {
+                    if let Some(page) = document.pages.last_mut() {
+                        page.blocks.push(Block {
+                            bbox: BBox::from_attributes(&attributes),
+                            lines: Vec::new(),
+                        });
+                    }
+                }
+                "line" => {
+                    if let Some(block) = document
+                        .pages
+                        .last_mut()
+                        .and_then(|page| page.blocks.last_mut())
+                    {
+                        block.lines.push(Line {
+                            bbox: BBox::from_attributes(&attributes),
+                            words: Vec::new(),
+                        });
+                    }
+                }
+                "word" => {
+                    word = Some(Word {
+                        bbox: BBox::from_attributes(&attributes),
+                        text: String::new(),
+                        font_size: None,
+                    })
+                }
+                _ => {}
+            },
+            Token::Text(text) => {
+                if let Some(word) = &mut word {
+                    word.text.push_str(&text);
+                }
+            }
+            Token::End(name) => {
+                if name == "word" {
+                    if let (Some(word), Some(line)) = (
+                        word.take(),
+                        document
+                            .pages
+                            .last_mut()
+                            .and_then(|page| page.blocks.last_mut())
+                            .and_then(|block| block.lines.last_mut()),
+                    ) {
+                        line.words.push(word);
+                    }
+                }
+            }
+        }
+    }
+
+    document
+}
+
+/// A run of text in one font, with its box in points.
+struct FontRun {
+    page: usize,
+    bbox: BBox,
+    size: f64,
+}
+
+/// Gives the words of `document` the font sizes of the runs of text in the XML that
+/// `pdftohtml -xml -zoom 1` writes, which measures in points like pdftotext.
+///
+/// A word gets the size of the first run on its page that its center is in, allowing for
+/// pdftohtml rounding the boxes to whole points. Words that no run covers keep no size.
+pub fn add_font_sizes(document: &mut Document, xml: &str) {
+    // the fonts are defined once, on the first page that uses them
+    let mut fonts: BTreeMap<String, f64> = BTreeMap::new();
+    let mut runs: Vec<FontRun> = Vec::new();
+    let mut page = 0;
+    for token in xml::tokenize(xml) {
+        let Token::Start(name, attributes) = token else {
+            continue;
+        };
+        let get = |name: &str| {
+            attributes
+                .get(name)
+                .and_then(|value| value.parse::<f64>().ok())
+        };
+        match name.as_str() {
+            "page" => page = get("number").map_or(page + 1, |number| number as usize),
+            "fontspec" => {
+                if let (Some(id), Some(size)) = (attributes.get("id"), get("size")) {
+                    fonts.insert(id.clone(), size);
+                }
+            }
+            "text" => {
+                let size = attributes.get("font").and_then(|font| fonts.get(font));
+                if let (Some(&size), Some(left), Some(top), Some(width), Some(height)) =
+                    (size, get("left"), get("top"), get("width"), get("height"))
+                {
+                    runs.push(FontRun {
+                        page,
+                        bbox: BBox {
+                            x_min: left,
+                            y_min: top,
+                            x_max: left + width,
+                            y_max: top + height,
+                        },
+                        size,
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    const TOLERANCE: f64 = 1.0;
+    for page in &mut document.pages {
+        let page_runs: Vec<&FontRun> = runs.iter().filter(|run| run.page == page.number).collect();
+        let words = page
+            .blocks
+            .iter_mut()
+            .flat_map(|block| &mut block.lines)
+            .flat_map(|line| &mut line.words);
+        for word in words {
+            let x = (word.bbox.x_min + word.bbox.x_max) / 2.0;
+            let y = (word.bbox.y_min + word.bbox.y_max) / 2.0;
+            word.font_size = page_runs
+                .iter()
+                .find(|run| {
+                    x >= run.bbox.x_min - TOLERANCE
+                        && x <= run.bbox.x_max + TOLERANCE
+                        && y >= run.bbox.y_min - TOLERANCE
+                        && y <= run.bbox.y_max + TOLERANCE
+                })
+                .map(|run| run.size);
+        }
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[allow(dead_code)]
+    const TWO_COLUMNS: &str = r#"<!DOCTYPE html PUBLIC "-//W3C//DTD XHTML 1.0 Transitional//EN" "http://www.w3.org/TR/xhtml1/DTD/xhtml1-transitional.dtd">
+<html xmlns="http://www.w3.org/1999/xhtml">
+<head>
+<title></title>
+<meta name="Producer" content="LaTeX &amp; friends"/>
+</head>
+<body>
+<doc>
+  <page width="612.000000" height="792.000000">
+    <flow>
+      <block xMin="72.0" yMin="50.0" xMax="540.0" yMax="70.0">
+        <line xMin="72.0" yMin="50.0" xMax="540.0" yMax="70.0">
+          <word xMin="72.0" yMin="50.0" xMax="140.0" yMax="70.0">Title</word>
+        </line>
+      </block>
+    </flow>
+    <flow>
+      <block xMin="320.0" yMin="100.0" xMax="540.0" yMax="112.0">
+        <line xMin="320.0" yMin="100.0" xMax="540.0" yMax="112.0">
+          <word xMin="320.0" yMin="100.0" xMax="360.0" yMax="112.0">Right</word>
+        </line>
+      </block>
+      <block xMin="72.0" yMin="200.0" xMax="290.0" yMax="212.0">
+        <line xMin="72.0" yMin="200.0" xMax="290.0" yMax="212.0">
+          <word xMin="72.0" yMin="200.0" xMax="100.0" yMax="212.0">Below</word>
+        </line>
+      </block>
+      <block xMin="72.0" yMin="100.0" xMax="290.0" yMax="124.0">
+        <line xMin="72.0" yMin="100.0" xMax="290.0" yMax="112.0">
+          <word xMin="72.0" yMin="100.0" xMax="110.0" yMax="112.0">Tom</word>
+          <word xMin="115.0" yMin="100.0" xMax="160.0" yMax="112.0">&amp;</word>
+          <word xMin="165.0" yMin="100.0" xMax="200.0" yMax="112.0">Jerry&#39;s</word>
+        </line>
+        <line xMin="72.0" yMin="112.0" xMax="290.0" yMax="124.0">
+          <word xMin="72.0" yMin="112.0" xMax="130.0" yMax="124.0">Left</word>
+        </line>
+      </block>
+    </flow>
+  </page>
+  <page width="612.000000" height="792.000000">
+  </page>
+</doc>
+</body>
+</html>
+"#;
+
+    #[test]
+    fn test_parse_bbox_layout() {
+        let document = parse(TWO_COLUMNS);
+        assert_eq!(document.pages.len(), 2);
+        assert_eq!(document.pages[1].number, 2);
+
+        let page = &document.pages[0];
+        assert_eq!(page.width, 612.0);
+        assert_eq!(page.blocks.len(), 4);
+        assert_eq!(page.blocks[3].text(), "Tom & Jerry's\nLeft");
+        assert_eq!(page.blocks[3].lines[0].word_height(), 12.0);
+        assert_eq!(
+            page.blocks[1].bbox,
+            BBox {
+                x_min: 320.0,
+                y_min: 100.0,
+                x_max: 540.0,
+                y_max: 112.0
+            }
+        );
+    }
+
+    #[test]
+    fn test_add_font_sizes() {
+        // the title and the left column, the right column is not covered by any run
+        let fonts = r##"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE pdf2xml SYSTEM "pdf2xml.dtd">
+<pdf2xml producer="poppler" version="22.12.0">
+<page number="1" position="absolute" top="0" left="0" height="792" width="612">
+	<fontspec id="0" size="18" family="Times" color="#000000"/>
+	<fontspec id="1" size="10" family="Times" color="#000000"/>
+<text top="51" left="72" width="68" height="19" font="0"><b>Title</b></text>
+<text top="100" left="72" width="128" height="12" font="1">Tom &amp; Jerry's</text>
+<text top="112" left="72" width="58" height="12" font="0">Left</text>
+<text top="200" left="72" width="28" height="12" font="1">Below</text>
+</page>
+<page number="2" position="absolute" top="0" left="0" height="792" width="612">
+</page>
+</pdf2xml>
+"##;
+        let mut document = parse(TWO_COLUMNS);
+        add_font_sizes(&mut document, fonts);
+
+        let page = &document.pages[0];
+        assert_eq!(page.blocks[0].lines[0].words[0].font_size, Some(18.0));
+        assert_eq!(page.blocks[1].lines[0].words[0].font_size, None);
+        let block = &page.blocks[3];
+        assert!(block.lines[0]
+            .words
+            .iter()
+            .all(|word| word.font_size == Some(10.0)));
+        assert_eq!(block.lines[0].font_size(), Some(10.0));
+        assert_eq!(block.lines[1].font_size(), Some(18.0));
+        assert_eq!(page.blocks[1].lines[0].font_size(), None);
+    }
+
+    #[test]
+    fn test_columns() {
+        let document = parse(TWO_COLUMNS);
+        let columns: Vec<Vec<String>> = document.pages[0]
+            .columns()
+            .iter()
+            .map(|column| column.iter().map(|block| block.text()).collect())
+            .collect();
+        assert_eq!(
+            columns,
+            vec![vec!["Title", "Tom & Jerry's\nLeft", "Below"], vec!["Right"]]
+        );
+    }
+}
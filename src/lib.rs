@@ -0,0 +1,59 @@
+//! Extracts the text of pdf files and translates it with Google Cloud Translation.
+//!
+//! The `pdf-translator` binary is a thin command line interface over this library, which
+//! can be used to embed the same pipeline in other programs:
+//!
+//! 1. [`pdf_reader::PdfReader`] extracts the text of a pdf with poppler, or with
+//!    [`pdf_reader::PdfReader::read_layout`] the position and font size of every word as a
+//!    [`layout::Document`],
+//! 2. [`segmenter`] splits it into the segments that are translated,
+//! 3. a [`translator::TranslationBackend`] translates them, [`translator::GoogleBackend`] using
+//!    the settings of a [`config::Config`] profile,
+//! 4. [`output::write`] writes the translation as text or JSON, the latter optionally with the
+//!    title, keywords and outline that [`pdf_reader::PdfReader::read_metadata`] reads and
+//!    [`metadata::Metadata::translate`] translates, and [`annotations::Annotations`] writes
+//!    the translated comments and form field tooltips into a copy of the pdf.
+//!
+//! Failures are reported as an [`Error`] that names the file, page, segment or backend
+//! involved and maps to the exit code of the binary.
+//!
+//! ```no_run
+//! use pdf_translator::{cache, job, pdf_reader::PdfReader, translator};
+//!
+//! # async fn example() -> pdf_translator::Result<()> {
+//! let reader = PdfReader::new("paper.pdf")?;
+//! let backend = translator::GoogleBackend::from_config()?;
+//! let input = translator::TranslateInput {
+//!     formatted_content: reader.get_content(),
+//!     source: "en".to_string(),
+//!     target: "sv".to_string(),
+//!     job: job::JobManifest::open(&job::hash_file("paper.pdf".as_ref())?, "en", "sv", false)?,
+//!     cache: cache::TranslationCache::default().shared(),
+//! };
+//! let translated = translator::translate_with(&backend, input).await?;
+//! pdf_translator::output::write("paper.sv.txt".as_ref(), Default::default(), &translated, None)?;
+//! # Ok(())
+//! # }
+//! ```
+
+pub mod annotations;
+pub mod auth;
+pub mod batch;
+pub mod cache;
+pub mod config;
+pub mod doctor;
+pub mod error;
+pub mod install;
+pub mod job;
+pub mod languages;
+pub mod layout;
+pub mod metadata;
+pub mod output;
+pub mod pdf_reader;
+pub mod program;
+pub mod secrets;
+pub mod segmenter;
+pub mod translator;
+pub mod xml;
+
+pub use error::{Error, Result};
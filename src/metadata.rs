@@ -0,0 +1,229 @@
+//! The document info and the outline of a pdf, read from what `pdfinfo` prints and from the
+//! outline `pdftohtml -xml` writes, and translated along with the text.
+//!
+//! The translation goes into the JSON output and, with the annotations, into the copy of the
+//! pdf that [`crate::annotations::Annotations::save`] writes.
+
+use crate::cache;
+use crate::error;
+use crate::translator::{self, TranslationBackend};
+use crate::xml::{self, Token};
+use serde::Serialize;
+
+/// The parts of a pdf besides its text that readers see: the document info shown as the
+/// title of the window and in the document properties, and the outline.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct Metadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subject: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keywords: Option<String>,
+    /// The bookmarks of the pdf, in the order they are shown
+    pub outline: Vec<OutlineEntry>,
+}
+
+/// A bookmark in the outline and the bookmarks nested under it.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct OutlineEntry {
+    pub title: String,
+    /// The page the bookmark points to, starting at 1, if it points to a page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub page: Option<usize>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub children: Vec<OutlineEntry>,
+}
+
+impl OutlineEntry {
+    fn texts_mut<'a>(&'a mut self, texts: &mut Vec<&'a mut String>) {
+        texts.push(&mut self.title);
+        for child in &mut self.children {
+            child.texts_mut(texts);
+        }
+    }
+}
+
+impl Metadata {
+    /// Whether the pdf has neither document info nor an outline.
+    pub fn is_empty(&self) -> bool {
+        self.title.is_none()
+            && self.subject.is_none()
+            && self.keywords.is_none()
+            && self.outline.is_empty()
+    }
+
+    /// Every text that is translated: the title, subject, keywords and bookmark titles.
+    fn texts_mut(&mut self) -> Vec<&mut String> {
+        let mut texts: Vec<&mut String> = [&mut self.title, &mut self.subject, &mut self.keywords]
+            .into_iter()
+            .filter_map(Option::as_mut)
+            .collect();
+        for entry in &mut self.outline {
+            entry.texts_mut(&mut texts);
+        }
+        texts
+    }
+
+    /// Translates the document info and the bookmark titles into `target`, reusing and
+    /// filling `cache` like the text of the document.
+    ///
+    /// `source` can be [`translator::AUTO_DETECT`] to let the backend detect the language of
+    /// every text.
+    pub async fn translate(
+        &self,
+        backend: &dyn TranslationBackend,
+        source: &str,
+        target: &str,
+        cache: &cache::SharedCache,
+    ) -> error::Result<Metadata> {
+        let mut translated = self.clone();
+        for text in translated.texts_mut() {
+            *text = translator::translate_cached(backend, text, source, target, cache).await?;
+        }
+
+        Ok(translated)
+    }
+}
+
+/// Reads the title, subject and keywords from the `Key: value` lines `pdfinfo` prints,
+/// leaving out the ones that are missing or empty.
+pub fn parse_info(info: &str) -> Metadata {
+    let mut metadata = Metadata::default();
+    for line in info.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() {
+            continue;
+        }
+        let field = match key {
+            "Title" => &mut metadata.title,
+            "Subject" => &mut metadata.subject,
+            "Keywords" => &mut metadata.keywords,
+            _ => continue,
+        };
+        *field = Some(value.to_string());
+    }
+    metadata
+}
+
+/// Reads the outline from the XML `pdftohtml -xml` writes.
+///
+/// The outline is an `<outline>` element of `<item page="..">` bookmarks, where the
+/// children of a bookmark are in an `<outline>` element right after its item.
+pub fn parse_outline(xml: &str) -> Vec<OutlineEntry> {
+    // the entries of every outline element that is still open, the innermost last
+    let mut levels: Vec<Vec<OutlineEntry>> = Vec::new();
+    let mut entry: Option<OutlineEntry> = None;
+
+    for token in xml::tokenize(xml) {
+        match token {
+            Token::Start(name, attributes) => match name.as_str() {
+                "outline" => levels.push(Vec::new()),
+                "item" if !levels.is_empty() => {
+                    entry = Some(OutlineEntry {
+                        title: String::new(),
+                        page: attributes.get("page").and_then(|page| page.parse().ok()),
+                        children: Vec::new(),
+                    })
+                }
+                _ => {}
+            },
+            Token::Text(text) => {
+                if let Some(entry) = &mut entry {
+                    entry.title.push_str(&text);
+                }
+            }
+            Token::End(name) => match name.as_str() {
+                "item" => {
+                    if let (Some(mut entry), Some(level)) = (entry.take(), levels.last_mut()) {
+                        entry.title = entry.title.trim().to_string();
+                        level.push(entry);
+                    }
+                }
+                "outline" => {
+                    let Some(entries) = levels.pop() else {
+                        continue;
+                    };
+                    let Some(parent) = levels.last_mut() else {
+                        // the outline of the document is complete
+                        return entries;
+                    };
+                    match parent.last_mut() {
+                        Some(item) => item.children.extend(entries),
+                        None => parent.extend(entries),
+                    }
+                }
+                _ => {}
+            },
+        }
+    }
+
+    // an outline that was cut off still has its entries
+    levels.into_iter().next().unwrap_or_default()
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_parse_info() {
+        let info = "Title:           Annual report: 2023\n\
+                    Subject:         \n\
+                    Keywords:        finance, results\n\
+                    Author:          Jane Doe\n\
+                    Pages:           12\n";
+        assert_eq!(
+            parse_info(info),
+            Metadata {
+                title: Some("Annual report: 2023".to_string()),
+                subject: None,
+                keywords: Some("finance, results".to_string()),
+                outline: Vec::new(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_outline() {
+        let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<!DOCTYPE pdf2xml SYSTEM "pdf2xml.dtd">
+<pdf2xml producer="poppler" version="22.12.0">
+<outline>
+<item page="1">Introduction</item>
+<item page="2">Methods &amp; data</item>
+<outline>
+<item page="2">Sampling</item>
+<item>Sources</item>
+</outline>
+<item page="5">Results</item>
+</outline>
+<page number="1" position="absolute" top="0" left="0" height="1263" width="892">
+<text top="100" left="100" width="200" height="20" font="0"><b>Introduction</b></text>
+</page>
+</pdf2xml>
+"#;
+        let outline = parse_outline(xml);
+        let titles: Vec<&str> = outline.iter().map(|entry| entry.title.as_str()).collect();
+        assert_eq!(titles, vec!["Introduction", "Methods & data", "Results"]);
+        assert_eq!(
+            outline[1].children,
+            vec![
+                OutlineEntry {
+                    title: "Sampling".to_string(),
+                    page: Some(2),
+                    children: Vec::new(),
+                },
+                OutlineEntry {
+                    title: "Sources".to_string(),
+                    page: None,
+                    children: Vec::new(),
+                },
+            ]
+        );
+        assert!(parse_outline("<pdf2xml><page number=\"1\"></page></pdf2xml>").is_empty());
+    }
+}
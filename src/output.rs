@@ -0,0 +1,277 @@
+//! The `output` module which decides where translated files are written and writes them.
+
+use crate::error;
+use crate::metadata::Metadata;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs;
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// The default naming template, e.g. `paper.pdf` translated to Swedish becomes `paper.sv.txt`.
+pub const DEFAULT_NAME_TEMPLATE: &str = "{stem}.{target}.{ext}";
+
+/// The path that stands for stdin as the input and for stdout as the output.
+pub const STDIO: &str = "-";
+
+/// Set when the translation is written to stdout, so status messages do not end up in it.
+static STATUS_TO_STDERR: AtomicBool = AtomicBool::new(false);
+
+/// Sends the status messages printed with [`status!`](crate::status) to stderr instead of
+/// stdout, for when the translation itself is written to stdout.
+pub fn status_to_stderr(enabled: bool) {
+    STATUS_TO_STDERR.store(enabled, Ordering::Relaxed);
+}
+
+#[doc(hidden)]
+pub fn print_status(args: fmt::Arguments) {
+    if STATUS_TO_STDERR.load(Ordering::Relaxed) {
+        eprintln!("{}", args);
+    } else {
+        println!("{}", args);
+    }
+}
+
+/// Prints a status message like `println!`, on stderr once the translation goes to stdout.
+#[macro_export]
+macro_rules! status {
+    ($($arg:tt)*) => {
+        $crate::output::print_status(format_args!($($arg)*))
+    };
+}
+
+/// The format the translation is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    /// Plain text, one `<line number>: <text>` entry per line
+    Txt,
+    /// A JSON array of `{ "line": .., "text": .. }` objects, or with the translated
+    /// metadata an object of the title, subject, keywords, outline and those `lines`
+    Json,
+}
+
+impl OutputFormat {
+    /// The file extension used for the format.
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OutputFormat::Txt => "txt",
+            OutputFormat::Json => "json",
+        }
+    }
+}
+
+/// The values that can be used as placeholders in a naming template.
+pub struct NameParts<'a> {
+    /// The input file name without its extension, `{stem}`
+    pub stem: &'a str,
+    /// The source language, `{source}`
+    pub source: &'a str,
+    /// The target language, `{target}`
+    pub target: &'a str,
+    /// The extension of the output format, `{ext}`
+    pub ext: &'a str,
+}
+
+/// Replaces `{stem}`, `{source}`, `{target}` and `{ext}` in `template`.
+pub fn render_template(template: &str, parts: &NameParts) -> String {
+    template
+        .replace("{stem}", parts.stem)
+        .replace("{source}", parts.source)
+        .replace("{target}", parts.target)
+        .replace("{ext}", parts.ext)
+}
+
+/// Works out the path of the translated file.
+///
+/// Without `--output` the rendered template is placed in the current directory. When
+/// `--output` points at a directory (existing, or written with a trailing separator) the
+/// rendered template is placed inside it, otherwise it is used as the file path itself.
+/// Placeholders are also expanded in `--output`, and `-` stands for stdout.
+pub fn resolve_output_path(output: Option<&str>, template: &str, parts: &NameParts) -> PathBuf {
+    let file_name = render_template(template, parts);
+
+    match output {
+        None => PathBuf::from(file_name),
+        Some(STDIO) => PathBuf::from(STDIO),
+        Some(output) => {
+            let rendered = render_template(output, parts);
+            let is_dir = rendered.ends_with('/')
+                || rendered.ends_with(std::path::MAIN_SEPARATOR)
+                || Path::new(&rendered).is_dir();
+
+            if is_dir {
+                Path::new(&rendered).join(file_name)
+            } else {
+                PathBuf::from(rendered)
+            }
+        }
+    }
+}
+
+/// The file name of `input` without its extension, used for the `{stem}` placeholder.
+pub fn file_stem(input: &Path) -> String {
+    input
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_else(|| "output".to_string())
+}
+
+#[derive(Serialize)]
+struct JsonLine<'a> {
+    line: usize,
+    text: &'a str,
+}
+
+#[derive(Serialize)]
+struct JsonDocument<'a> {
+    #[serde(flatten)]
+    metadata: &'a Metadata,
+    lines: Vec<JsonLine<'a>>,
+}
+
+/// Writes the translated lines to `path` in the given format, creating parent directories,
+/// or to stdout when `path` is `-`.
+///
+/// The `metadata` of the document is only written in the JSON format, which then becomes an
+/// object holding the metadata and the lines instead of an array of the lines.
+pub fn write(
+    path: &Path,
+    format: OutputFormat,
+    lines: &[(usize, String)],
+    metadata: Option<&Metadata>,
+) -> error::Result<()> {
+    if path == Path::new(STDIO) {
+        let mut stdout = io::stdout().lock();
+        write_to(&mut stdout, format, lines, metadata)?;
+        stdout.flush()?;
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        if !parent.as_os_str().is_empty() {
+            fs::create_dir_all(parent).map_err(|e| error::Error::io(parent, e))?;
+        }
+    }
+
+    fs::File::create(path)
+        .and_then(|mut file| write_to(&mut file, format, lines, metadata))
+        .map_err(|e| error::Error::io(path, e))
+}
+
+fn write_to(
+    file: &mut impl Write,
+    format: OutputFormat,
+    lines: &[(usize, String)],
+    metadata: Option<&Metadata>,
+) -> io::Result<()> {
+    match format {
+        OutputFormat::Txt => {
+            for (line_number, line) in lines {
+                writeln!(file, "{}: {}", line_number, line)?;
+            }
+        }
+        OutputFormat::Json => {
+            let json: Vec<JsonLine> = lines
+                .iter()
+                .map(|(line, text)| JsonLine { line: *line, text })
+                .collect();
+            match metadata {
+                Some(metadata) => serde_json::to_writer_pretty(
+                    &mut *file,
+                    &JsonDocument {
+                        metadata,
+                        lines: json,
+                    },
+                )?,
+                None => serde_json::to_writer_pretty(&mut *file, &json)?,
+            }
+            writeln!(file)?;
+        }
+    }
+    Ok(())
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_render_default_template() {
+        let parts = NameParts {
+            stem: "Winch (2004)",
+            source: "en",
+            target: "sv",
+            ext: OutputFormat::Txt.extension(),
+        };
+        assert_eq!(
+            render_template(DEFAULT_NAME_TEMPLATE, &parts),
+            "Winch (2004).sv.txt"
+        );
+    }
+
+    #[test]
+    fn test_resolve_output_path() {
+        let input = Path::new("test-files/example.pdf");
+        let parts = NameParts {
+            stem: &file_stem(input),
+            source: "en",
+            target: "sv",
+            ext: "json",
+        };
+
+        assert_eq!(
+            resolve_output_path(None, DEFAULT_NAME_TEMPLATE, &parts),
+            PathBuf::from("example.sv.json")
+        );
+        assert_eq!(
+            resolve_output_path(Some("out/"), DEFAULT_NAME_TEMPLATE, &parts),
+            Path::new("out/").join("example.sv.json")
+        );
+        assert_eq!(
+            resolve_output_path(Some("out/{target}.txt"), DEFAULT_NAME_TEMPLATE, &parts),
+            PathBuf::from("out/sv.txt")
+        );
+        assert_eq!(
+            resolve_output_path(Some(STDIO), DEFAULT_NAME_TEMPLATE, &parts),
+            PathBuf::from("-")
+        );
+        // only the extension is dropped, not a '.pdf' elsewhere in the name
+        assert_eq!(
+            file_stem(Path::new("drafts.pdf/v2.pdf.old.pdf")),
+            "v2.pdf.old"
+        );
+    }
+
+    #[test]
+    fn test_write_json_with_metadata() {
+        let lines = vec![(0, "Hej".to_string())];
+        let mut json = Vec::new();
+        write_to(&mut json, OutputFormat::Json, &lines, None).unwrap();
+        assert!(serde_json::from_slice::<serde_json::Value>(&json)
+            .unwrap()
+            .is_array());
+
+        let metadata = Metadata {
+            title: Some("Rapport".to_string()),
+            outline: vec![crate::metadata::OutlineEntry {
+                title: "Inledning".to_string(),
+                page: Some(1),
+                children: Vec::new(),
+            }],
+            ..Default::default()
+        };
+        let mut json = Vec::new();
+        write_to(&mut json, OutputFormat::Json, &lines, Some(&metadata)).unwrap();
+        assert_eq!(
+            serde_json::from_slice::<serde_json::Value>(&json).unwrap(),
+            serde_json::json!({
+                "title": "Rapport",
+                "outline": [{ "title": "Inledning", "page": 1 }],
+                "lines": [{ "line": 0, "text": "Hej" }],
+            })
+        );
+    }
+}
@@ -0,0 +1,201 @@
+//! Extracts the text of a pdf with poppler's `pdftotext`, keeping the layout of every page,
+//! reads the positions and font sizes of the text into a [`layout::Document`], or reads the
+//! document info and outline into a [`metadata::Metadata`] with `pdfinfo` and `pdftohtml`.
+
+use crate::error::{self, Error};
+use crate::layout;
+use crate::metadata;
+use crate::segmenter::{self, Segment};
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// The text of a pdf, split into segments.
+pub struct PdfReader {
+    content: Vec<Segment>,
+    page_count: usize,
+}
+
+impl PdfReader {
+    /// Extracts the text of the pdf at `path`.
+    pub fn new(path: &str) -> error::Result<PdfReader> {
+        PdfReader::open(path, None)
+    }
+
+    /// Extracts the text of the pdf at `path`, decrypting it with `password` if it is
+    /// encrypted. The password can be either the user or the owner password.
+    pub fn open(path: &str, password: Option<&str>) -> error::Result<PdfReader> {
+        let text = PdfReader::read_pdf(path, password)?;
+        let content = segmenter::split_lines(&text);
+        // pdftotext ends every page with a form feed
+        let page_count = text.matches('\u{c}').count();
+
+        Ok(PdfReader {
+            content,
+            page_count,
+        })
+    }
+
+    /// The segments of the text, one per line.
+    pub fn get_content(&self) -> Vec<Segment> {
+        self.content.clone()
+    }
+
+    /// The number of pages in the pdf.
+    pub fn get_page_count(&self) -> usize {
+        self.page_count
+    }
+
+    /// Extracts the text of the pdf at `path` together with the position of every block,
+    /// line and word and the font size of the words, for writers that keep the layout and
+    /// for finding columns.
+    pub fn read_layout(path: &str, password: Option<&str>) -> error::Result<layout::Document> {
+        let output = PdfReader::run_pdftotext(path, "-bbox-layout", password)?;
+        let xml = String::from_utf8(output).map_err(|e| {
+            let valid = &e.as_bytes()[..e.utf8_error().valid_up_to()];
+            let page = String::from_utf8_lossy(valid).matches("<page ").count();
+            not_utf8(path, page.max(1))
+        })?;
+        let mut document = layout::parse(&xml);
+
+        // pdftohtml measures in pixels at 1.5 times the size by default, -zoom 1 gives points
+        let fonts = run_poppler(
+            "pdftohtml",
+            &["-xml", "-i", "-q", "-zoom", "1", "-stdout"],
+            path,
+            &[],
+            password,
+        )?;
+        layout::add_font_sizes(&mut document, &String::from_utf8_lossy(&fonts));
+        Ok(document)
+    }
+
+    /// Reads the title, subject and keywords of the pdf at `path` and its outline, the tree
+    /// of bookmarks shown next to the pages in pdf viewers.
+    pub fn read_metadata(path: &str, password: Option<&str>) -> error::Result<metadata::Metadata> {
+        let info = run_poppler("pdfinfo", &[], path, &[], password)?;
+        // pdftohtml writes the outline before the pages, with -i it skips extracting images
+        let xml = run_poppler(
+            "pdftohtml",
+            &["-xml", "-i", "-q", "-stdout"],
+            path,
+            &[],
+            password,
+        )?;
+
+        let mut metadata = metadata::parse_info(&String::from_utf8_lossy(&info));
+        metadata.outline = metadata::parse_outline(&String::from_utf8_lossy(&xml));
+        Ok(metadata)
+    }
+
+    /// Runs `pdftotext` in the text layout mode.
+    fn read_pdf(path: &str, password: Option<&str>) -> error::Result<String> {
+        let output = PdfReader::run_pdftotext(path, "-layout", password)?;
+        String::from_utf8(output).map_err(|e| {
+            // count the form feeds before the invalid bytes to find their page
+            let valid = &e.as_bytes()[..e.utf8_error().valid_up_to()];
+            not_utf8(
+                path,
+                valid.iter().filter(|&&byte| byte == b'\x0c').count() + 1,
+            )
+        })
+    }
+
+    /// Runs `pdftotext` with `mode` and `-` as the output file so the output is captured
+    /// from stdout instead of being written to a file next to the source pdf.
+    fn run_pdftotext(path: &str, mode: &str, password: Option<&str>) -> error::Result<Vec<u8>> {
+        run_poppler("pdftotext", &[mode], path, &["-"], password)
+    }
+}
+
+/// Runs the poppler `tool` on the pdf at `path` as `tool <options> <path> <outputs>` and
+/// returns what it wrote to stdout.
+///
+/// The `password` is tried as the user password and, if it is not, as the owner password,
+/// so only one of them is on the command line of the tool at a time.
+fn run_poppler(
+    tool: &str,
+    options: &[&str],
+    path: &str,
+    outputs: &[&str],
+    password: Option<&str>,
+) -> error::Result<Vec<u8>> {
+    let Some(password) = password else {
+        return run_poppler_with(tool, options, path, outputs, None);
+    };
+    match run_poppler_with(tool, options, path, outputs, Some(("-upw", password))) {
+        Err(Error::Encrypted { .. }) => {
+            run_poppler_with(tool, options, path, outputs, Some(("-opw", password)))
+        }
+        result => result,
+    }
+}
+
+/// Runs the poppler `tool` like [`run_poppler`], giving it the password as `(flag, password)`.
+fn run_poppler_with(
+    tool: &str,
+    options: &[&str],
+    path: &str,
+    outputs: &[&str],
+    password: Option<(&str, &str)>,
+) -> error::Result<Vec<u8>> {
+    let mut command = Command::new(tool);
+    command.args(options);
+    if let Some((flag, password)) = password {
+        command.arg(flag).arg(password);
+    }
+    let output = command
+        .arg(path)
+        .args(outputs)
+        .output()
+        .map_err(|e| match e.kind() {
+            ErrorKind::NotFound => Error::Install(format!(
+                "{} was not found, install poppler with 'pdf-translator install'",
+                tool
+            )),
+            _ => Error::io(path.as_ref(), e),
+        })?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        if stderr.contains("Incorrect password") {
+            return Err(Error::Encrypted {
+                file: PathBuf::from(path),
+                password_given: password.is_some(),
+            });
+        }
+        return Err(Error::Extract {
+            file: PathBuf::from(path),
+            page: None,
+            message: format!("{} failed: {}", tool, stderr.trim()),
+        });
+    }
+
+    Ok(output.stdout)
+}
+
+fn not_utf8(path: &str, page: usize) -> Error {
+    Error::Extract {
+        file: PathBuf::from(path),
+        page: Some(page),
+        message: "pdftotext returned text that is not valid UTF-8".to_string(),
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_read_basic_pdf() {
+        let path = format!("{}/test-files/example.pdf", env!("CARGO_MANIFEST_DIR"));
+        let pdf_reader = PdfReader::new(&path).expect("Error reading pdf");
+        let content = pdf_reader.get_content();
+        let correct_content: Vec<(usize, String)> =
+            vec![(0, "Hello World!".to_string()), (1, "\u{c}".to_string())];
+
+        // compare correct content with the content from the pdf
+        assert_eq!(content, correct_content);
+        assert_eq!(pdf_reader.get_page_count(), 1);
+    }
+}
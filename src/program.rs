@@ -0,0 +1,542 @@
+//! Ties the pipeline together: resolves the languages, extracts, translates and writes every
+//! input file, and reports the results.
+
+use crate::annotations;
+use crate::batch;
+use crate::cache;
+use crate::config;
+use crate::error::{self, Error};
+use crate::job;
+use crate::languages;
+use crate::metadata;
+use crate::output;
+use crate::pdf_reader;
+use crate::status;
+use crate::translator;
+use std::io::{IsTerminal, Read};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+pub struct Args {
+    pub file_path: String,
+    pub recursive: bool,
+    pub source: String,
+    pub targets: Vec<String>,
+    pub output: Option<String>,
+    pub name_template: String,
+    pub format: Option<output::OutputFormat>,
+    pub resume: bool,
+    pub detect: DetectMode,
+    pub pdf_password: Option<String>,
+    /// Also translate the document info and outline, written into the JSON output and the
+    /// copy of the pdf that `annotations` writes
+    pub metadata: bool,
+    /// Also translate the comments and form field tooltips into a copy of the pdf
+    pub annotations: bool,
+}
+
+/// How the source language is detected when it is given as `auto`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum DetectMode {
+    /// Detect the language once from a sample of the document
+    Document,
+    /// Let the backend detect the language of every line, for mixed-language documents
+    Segment,
+}
+
+/// How much text is sent to the backend when detecting the language of a whole document.
+const DETECTION_SAMPLE_CHARS: usize = 2000;
+
+/// Detects the source language of a whole document from the start of its text.
+async fn detect_document_language(content: &[(usize, String)]) -> error::Result<String> {
+    let mut sample = String::new();
+    for (_, line) in content {
+        if sample.chars().count() >= DETECTION_SAMPLE_CHARS {
+            break;
+        }
+        if !line.trim().is_empty() {
+            sample.push_str(line.trim());
+            sample.push('\n');
+        }
+    }
+
+    let (language, confidence) = translator::detect_language(&sample).await?;
+    status!(
+        "Detected source language: {} (confidence {:.2})",
+        language,
+        confidence
+    );
+    Ok(language)
+}
+
+/// Extracts the text of a pdf, asking for its password on the terminal when it is encrypted
+/// and none was given, and returns the password that opened it.
+fn read_pdf(
+    file_path: &Path,
+    password: Option<&str>,
+) -> error::Result<(pdf_reader::PdfReader, Option<String>)> {
+    let path = file_path.to_string_lossy();
+    match pdf_reader::PdfReader::open(&path, password) {
+        Err(Error::Encrypted {
+            password_given: false,
+            ..
+        }) if std::io::stderr().is_terminal() => {
+            let password =
+                rpassword::prompt_password(format!("Password of '{}': ", file_path.display()))?;
+            let pdf_reader = pdf_reader::PdfReader::open(&path, Some(&password))?;
+            Ok((pdf_reader, Some(password)))
+        }
+        result => Ok((result?, password.map(str::to_string))),
+    }
+}
+
+/// Translates a single pdf file into every target language and writes one file per
+/// language to `output`, returning the number of pages and characters that were translated.
+///
+/// The text is extracted once and the languages are translated in parallel, sharing `cache`.
+async fn translate_file(
+    file_path: &Path,
+    output: Option<&str>,
+    args: &Args,
+    cache: &cache::SharedCache,
+) -> error::Result<(usize, usize)> {
+    let stem = output::file_stem(file_path);
+    let format = args.format.unwrap_or_default();
+    let output_path = |source: &str, target: &str| {
+        output::resolve_output_path(
+            output,
+            &args.name_template,
+            &output::NameParts {
+                stem: &stem,
+                source,
+                target,
+                ext: format.extension(),
+            },
+        )
+    };
+
+    if output == Some(output::STDIO) && args.targets.len() > 1 {
+        return Err(Error::Input(
+            "Only one target language can be written to stdout".to_string(),
+        ));
+    }
+
+    // check before doing any work that the languages do not overwrite each other
+    let mut output_paths: Vec<_> = args
+        .targets
+        .iter()
+        .map(|target| output_path(&args.source, target))
+        .collect();
+    output_paths.sort();
+    output_paths.dedup();
+    if output_paths.len() != args.targets.len() {
+        return Err(Error::Input(
+            "The output file name must contain '{target}' when translating into several languages, check '--output' and '--name-template'"
+                .to_string(),
+        ));
+    }
+    if args.annotations {
+        if output == Some(output::STDIO) {
+            return Err(Error::Input(
+                "The pdf with the translated annotations cannot be written to stdout".to_string(),
+            ));
+        }
+        if output_paths.iter().any(|path| {
+            let pdf_path = annotated_pdf_path(path);
+            pdf_path == *path || is_same_file(&pdf_path, file_path)
+        }) {
+            return Err(Error::Input(
+                "The pdf with the translated annotations would overwrite the translation or the input, check '--output' and '--name-template'"
+                    .to_string(),
+            ));
+        }
+    }
+
+    let (pdf_reader, password) = read_pdf(file_path, args.pdf_password.as_deref())?;
+    let content = pdf_reader.get_content();
+    let metadata = if args.metadata {
+        let metadata = pdf_reader::PdfReader::read_metadata(
+            &file_path.to_string_lossy(),
+            password.as_deref(),
+        )?;
+        Some(metadata)
+    } else {
+        None
+    };
+    let annotations = if args.annotations {
+        match annotations::Annotations::read(file_path, password.as_deref()) {
+            // without annotations the copy is still written for the translated metadata
+            Ok(annotations)
+                if annotations.is_empty()
+                    && metadata.as_ref().map_or(true, metadata::Metadata::is_empty) =>
+            {
+                status!("No comments or form fields to translate in the pdf");
+                None
+            }
+            // shared by the targets, which only translate its texts
+            Ok(annotations) => Some(Arc::new(annotations)),
+            // the text could be extracted, so translate it without the annotations
+            Err(e @ Error::Extract { .. }) => {
+                status!(
+                    "Warning: the comments and form fields are not translated: {}",
+                    e
+                );
+                None
+            }
+            Err(e) => return Err(e),
+        }
+    } else {
+        None
+    };
+    let characters = content.iter().map(|(_, line)| line.chars().count()).sum();
+
+    let source = if args.source == translator::AUTO_DETECT && args.detect == DetectMode::Document {
+        detect_document_language(&content).await?
+    } else {
+        args.source.clone()
+    };
+
+    let input_hash = job::hash_file(file_path).map_err(|e| Error::io(file_path, e))?;
+    let mut tasks = tokio::task::JoinSet::new();
+    for target in &args.targets {
+        let request = translator::TranslateInput {
+            formatted_content: content.clone(),
+            source: source.clone(),
+            target: target.clone(),
+            job: job::JobManifest::open(&input_hash, &source, target, args.resume)?,
+            cache: cache.clone(),
+        };
+        let output_path = output_path(&source, target);
+        let target = target.clone();
+        let metadata = metadata.clone();
+        let annotations = annotations.clone();
+        tasks.spawn(async move {
+            let result = translate_document(request, metadata, annotations).await;
+            (target, output_path, result)
+        });
+    }
+
+    let mut errors = Vec::new();
+    while let Some(result) = tasks.join_next().await {
+        match result.map_err(|e| e.to_string())? {
+            (_, output_path, Ok(translated)) => {
+                output::write(
+                    &output_path,
+                    format,
+                    &translated.lines,
+                    translated.metadata.as_ref(),
+                )?;
+                if output_path != Path::new(output::STDIO) {
+                    status!("Translation complete: {}", output_path.display());
+                }
+                if let (Some(annotations), Some(translations)) =
+                    (&annotations, translated.annotations)
+                {
+                    let pdf_path = annotated_pdf_path(&output_path);
+                    annotations.save(
+                        &pdf_path,
+                        &translations,
+                        metadata.as_ref().zip(translated.metadata.as_ref()),
+                    )?;
+                    status!("Translated annotations: {}", pdf_path.display());
+                }
+            }
+            (target, _, Err(e)) => errors.push((target, e)),
+        }
+    }
+
+    if !errors.is_empty() {
+        let resumable = errors
+            .iter()
+            .any(|(target, _)| job::JobManifest::exists(&input_hash, &source, target));
+        return Err(Error::Targets { errors, resumable });
+    }
+
+    Ok((pdf_reader.get_page_count(), characters))
+}
+
+/// A document translated into one language.
+struct Translated {
+    lines: Vec<(usize, String)>,
+    metadata: Option<metadata::Metadata>,
+    /// The translations of the texts of the annotations, in their order
+    annotations: Option<Vec<String>>,
+}
+
+/// Translates the text of a document and, when they were read, its metadata and
+/// annotations into one language.
+async fn translate_document(
+    request: translator::TranslateInput,
+    metadata: Option<metadata::Metadata>,
+    annotations: Option<Arc<annotations::Annotations>>,
+) -> error::Result<Translated> {
+    let backend = translator::GoogleBackend::from_config()?;
+    let (source, target, cache) = (
+        request.source.clone(),
+        request.target.clone(),
+        request.cache.clone(),
+    );
+    let lines = translator::translate_with(&backend, request).await?;
+    let metadata = match metadata {
+        Some(metadata) => Some(
+            metadata
+                .translate(&backend, &source, &target, &cache)
+                .await?,
+        ),
+        None => None,
+    };
+    let annotations = match annotations {
+        Some(annotations) => Some(
+            annotations
+                .translate(&backend, &source, &target, &cache)
+                .await?,
+        ),
+        None => None,
+    };
+    Ok(Translated {
+        lines,
+        metadata,
+        annotations,
+    })
+}
+
+/// The copy of the pdf with the translated annotations is named like the translation, with
+/// the extension of a pdf.
+fn annotated_pdf_path(output_path: &Path) -> PathBuf {
+    output_path.with_extension("pdf")
+}
+
+/// Whether `a` and `b` are the same file, following `.`, `..` and symlinks. Of a file that
+/// does not exist yet the closest directory above it that does is followed.
+fn is_same_file(a: &Path, b: &Path) -> bool {
+    match (absolute_path(a), absolute_path(b)) {
+        (Some(a), Some(b)) => a == b,
+        _ => a == b,
+    }
+}
+
+/// `path` with its existing part canonicalized and the rest, which cannot hold symlinks,
+/// resolved by its components.
+fn absolute_path(path: &Path) -> Option<PathBuf> {
+    let path = std::env::current_dir().ok()?.join(path);
+    let mut existing = path.as_path();
+    let mut missing = Vec::new();
+    loop {
+        if let Ok(mut resolved) = std::fs::canonicalize(existing) {
+            for component in missing.into_iter().rev() {
+                match component {
+                    Component::ParentDir => {
+                        resolved.pop();
+                    }
+                    Component::Normal(name) => resolved.push(name),
+                    _ => {}
+                }
+            }
+            return Some(resolved);
+        }
+        missing.push(existing.components().next_back()?);
+        existing = existing.parent()?;
+    }
+}
+
+/// The environment variable holding the password of encrypted pdfs, which unlike
+/// `--pdf-password` does not show up in the process list.
+pub const PDF_PASSWORD_ENV: &str = "PDF_TRANSLATOR_PDF_PASSWORD";
+
+/// The name a pdf read from stdin is saved under, which makes `{stem}` `stdin`.
+const STDIN_FILE_NAME: &str = "stdin.pdf";
+
+/// Saves the pdf piped to stdin in a new temporary directory, as pdftotext needs a file it
+/// can seek in, and returns the directory.
+///
+/// The directory gets a random name and is only accessible to the current user, so other
+/// users can neither read the pdf nor plant a file or link in its place. It is removed when
+/// the returned value is dropped.
+fn read_stdin() -> error::Result<tempfile::TempDir> {
+    let mut pdf = Vec::new();
+    std::io::stdin().lock().read_to_end(&mut pdf)?;
+    if pdf.is_empty() {
+        return Err(Error::Input("Nothing was read from stdin".to_string()));
+    }
+
+    let dir = tempfile::Builder::new()
+        .prefix("pdf-translator-")
+        .tempdir()
+        .map_err(|e| Error::io(&std::env::temp_dir(), e))?;
+    let path = dir.path().join(STDIN_FILE_NAME);
+    std::fs::write(&path, pdf).map_err(|e| Error::io(&path, e))?;
+    Ok(dir)
+}
+
+fn save_cache(cache: &cache::SharedCache) {
+    if let Err(e) = cache
+        .lock()
+        .map_err(|e| e.to_string())
+        .and_then(|mut cache| cache.save().map_err(|e| e.to_string()))
+    {
+        status!("Error saving the translation cache: {}", e);
+    }
+}
+
+/// Translates the file, directory or glob pattern of `args`, failing with the error of the
+/// file or, in a batch, with the files that failed after printing a summary.
+pub async fn run(mut args: Args) -> error::Result<()> {
+    // a pdf read from stdin is translated to stdout unless an output is given
+    let from_stdin = args.file_path == output::STDIO;
+    if from_stdin && args.output.is_none() {
+        args.output = Some(output::STDIO.to_string());
+    }
+    output::status_to_stderr(args.output.as_deref() == Some(output::STDIO));
+
+    let config = config::Config::load_or_default()?;
+    let profile = config.profile_name();
+    if profile != config::DEFAULT_PROFILE && !config.has_profile(&profile) {
+        return Err(Error::Config(format!(
+            "No profile named '{}', create it with '--profile {} config setup ...'",
+            profile, profile
+        )));
+    }
+
+    if let Some(path) = config.project_file() {
+        status!("Using settings from {}", path.display());
+    }
+
+    // the profile fills in what was not given on the command line
+    if args.source.is_empty() {
+        args.source = config.get_source();
+    }
+    if args.targets.is_empty() {
+        args.targets = config.get_targets();
+    }
+    if args.name_template.is_empty() {
+        args.name_template = config.get_name_template();
+    }
+    if args.name_template.is_empty() {
+        args.name_template = output::DEFAULT_NAME_TEMPLATE.to_string();
+    }
+    args.format = args.format.or(config.get_format());
+    if args.metadata && args.format != Some(output::OutputFormat::Json) {
+        return Err(Error::Input(
+            "The metadata can only be written in the JSON format, add '--format json'".to_string(),
+        ));
+    }
+
+    if args.source.is_empty() {
+        status!("No source language provided, defaulting to 'en'");
+        args.source = "en".to_string();
+    }
+
+    if args.targets.is_empty() {
+        status!("No target language provided, defaulting to 'sv'");
+        args.targets = vec!["sv".to_string()];
+    }
+
+    // check the languages against the list of the backend, unless only the built-in table is
+    // at hand
+    let (supported, language_source) = languages::available(false).await;
+    let supported =
+        (language_source != languages::LanguageSource::BuiltIn).then_some(supported.as_slice());
+
+    if args.source != translator::AUTO_DETECT {
+        args.source = languages::resolve_supported(&args.source, supported)
+            .map_err(|e| Error::Input(format!("Invalid source language: {}", e)))?;
+    }
+
+    let mut targets: Vec<String> = Vec::new();
+    for target in &args.targets {
+        let code = languages::resolve_supported(target, supported)
+            .map_err(|e| Error::Input(format!("Invalid target language: {}", e)))?;
+        if !targets.contains(&code) {
+            targets.push(code);
+        }
+    }
+    args.targets = targets;
+
+    let cache = cache::TranslationCache::load()
+        .map_err(|e| format!("Error loading the translation cache: {}", e))?
+        .shared();
+
+    if from_stdin {
+        let dir = read_stdin()?;
+        let file_path = dir.path().join(STDIN_FILE_NAME);
+        let result = translate_file(&file_path, args.output.as_deref(), &args, &cache).await;
+        save_cache(&cache);
+        if let Err(e) = dir.close() {
+            status!("Error removing the copy of the pdf read from stdin: {}", e);
+        }
+        return result.map(|_| ());
+    }
+
+    let file_path = Path::new(&args.file_path);
+    if file_path.is_file() {
+        let result = translate_file(file_path, args.output.as_deref(), &args, &cache).await;
+        save_cache(&cache);
+        return result.map(|_| ()).map_err(|e| e.in_file(file_path));
+    }
+
+    let inputs = batch::collect_inputs(&args.file_path, args.recursive)?;
+    if inputs.is_empty() {
+        return Err(Error::Input(format!(
+            "No pdf files found in '{}'",
+            args.file_path
+        )));
+    }
+    if args.output.as_deref() == Some(output::STDIO) {
+        return Err(Error::Input(
+            "Only a single file can be written to stdout".to_string(),
+        ));
+    }
+
+    // the output is always a directory in batch mode, mirroring the input tree
+    let output_dir = Path::new(args.output.as_deref().unwrap_or("."));
+    let mut reports = Vec::new();
+    for input in inputs {
+        status!("Translating {}", input.path.display());
+        let dir = output_dir.join(&input.relative_dir);
+        let dir = format!("{}{}", dir.display(), std::path::MAIN_SEPARATOR);
+        let result = translate_file(&input.path, Some(&dir), &args, &cache).await;
+        save_cache(&cache);
+        reports.push(batch::FileReport {
+            path: input.path,
+            result,
+        });
+    }
+
+    batch::print_summary(&reports);
+
+    let total = reports.len();
+    let failures: Vec<_> = reports
+        .into_iter()
+        .filter_map(|report| report.result.err().map(|e| (report.path, e)))
+        .collect();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::Batch { total, failures })
+    }
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_is_same_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let input = dir.path().join("form.pdf");
+        std::fs::write(&input, b"%PDF-1.5").unwrap();
+
+        assert!(is_same_file(&dir.path().join(".").join("form.pdf"), &input));
+        assert!(is_same_file(
+            &dir.path().join("out").join("..").join("form.pdf"),
+            &input
+        ));
+        assert!(!is_same_file(&dir.path().join("form.sv.pdf"), &input));
+        #[cfg(unix)]
+        {
+            let link = dir.path().join("link.pdf");
+            std::os::unix::fs::symlink(&input, &link).unwrap();
+            assert!(is_same_file(&link, &input));
+        }
+    }
+}
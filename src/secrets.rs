@@ -0,0 +1,231 @@
+//! The `secrets` module which keeps the API key and access token out of the config file. They
+//! are stored in the OS keyring, or in a private secrets file when no keyring is available, and
+//! the config file only holds a reference to them. Environment variables take precedence.
+
+use crate::config;
+use crate::status;
+use std::collections::BTreeMap;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const KEYRING_SERVICE: &str = "pdf_translator";
+const KEYRING_PREFIX: &str = "keyring:";
+const FILE_PREFIX: &str = "file:";
+const ENV_PREFIX: &str = "env:";
+
+/// Where `config setup` and `config set` store the API key and access token, chosen with
+/// `--secret-store`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, clap::ValueEnum)]
+pub enum SecretStore {
+    /// The OS keyring (Secret Service, macOS Keychain or Windows Credential Manager),
+    /// falling back to the secrets file when it is not available
+    #[default]
+    Keyring,
+    /// A secrets file only readable by the current user, for headless machines and CI
+    File,
+}
+
+/// The environment variable that overrides a secret or setting, e.g. `PDF_TRANSLATOR_API_KEY`.
+pub fn env_var(name: &str) -> String {
+    format!("PDF_TRANSLATOR_{}", name.to_uppercase())
+}
+
+/// Whether a config value refers to a secret stored elsewhere rather than holding it.
+pub fn is_reference(value: &str) -> bool {
+    [KEYRING_PREFIX, FILE_PREFIX, ENV_PREFIX]
+        .iter()
+        .any(|prefix| value.starts_with(prefix))
+}
+
+/// Resolves the secret `name` from its value in the config file, which is either a
+/// reference or, for configs saved by older versions, the secret itself.
+pub fn resolve(name: &str, value: &str) -> String {
+    if let Ok(secret) = env::var(env_var(name)) {
+        if !secret.is_empty() {
+            return secret;
+        }
+    }
+
+    lookup(value).unwrap_or_else(|e| {
+        status!("Could not read the {} from '{}': {}", name, value, e);
+        String::new()
+    })
+}
+
+/// Reads the secret a config value refers to, or returns the value itself when it is not
+/// a reference. Unlike `resolve`, the environment variable overrides are not considered.
+pub fn lookup(value: &str) -> Result<String, String> {
+    if let Some(var) = value.strip_prefix(ENV_PREFIX) {
+        Ok(env::var(var).unwrap_or_default())
+    } else if let Some(key) = value.strip_prefix(KEYRING_PREFIX) {
+        keyring::Entry::new(KEYRING_SERVICE, key)
+            .and_then(|entry| entry.get_password())
+            .map_err(|e| e.to_string())
+    } else if let Some(key) = value.strip_prefix(FILE_PREFIX) {
+        get_secrets_path()
+            .map_err(|e| e.to_string())
+            .and_then(|path| read_secrets(&path).map_err(|e| e.to_string()))
+            .map(|secrets| secrets.get(key).cloned().unwrap_or_default())
+    } else {
+        Ok(value.to_string())
+    }
+}
+
+/// Removes the secret a config value refers to from the keyring or the secrets file.
+pub fn delete(value: &str) -> Result<(), Box<dyn std::error::Error>> {
+    if let Some(key) = value.strip_prefix(KEYRING_PREFIX) {
+        match keyring::Entry::new(KEYRING_SERVICE, key)?.delete_password() {
+            Ok(()) | Err(keyring::Error::NoEntry) => {}
+            Err(e) => return Err(e.into()),
+        }
+    } else if let Some(key) = value.strip_prefix(FILE_PREFIX) {
+        let path = get_secrets_path()?;
+        let mut secrets = read_secrets(&path)?;
+        if secrets.remove(key).is_some() {
+            write_secrets(&path, &secrets)?;
+        }
+    }
+    Ok(())
+}
+
+/// Hides all but the end of a secret so it can be shown. References are shown as they are.
+pub fn mask(value: &str) -> String {
+    let chars: Vec<char> = value.chars().collect();
+    if value.is_empty() || is_reference(value) {
+        value.to_string()
+    } else if chars.len() <= 12 {
+        "********".to_string()
+    } else {
+        let end: String = chars[chars.len() - 4..].iter().collect();
+        format!("********{}", end)
+    }
+}
+
+/// Stores the secret `name` and returns the reference to write to the config file instead.
+/// Empty values and values that already are references are returned unchanged.
+pub fn store(
+    name: &str,
+    secret: &str,
+    store: SecretStore,
+) -> Result<String, Box<dyn std::error::Error>> {
+    if secret.is_empty() || is_reference(secret) {
+        return Ok(secret.to_string());
+    }
+
+    if store == SecretStore::Keyring {
+        match keyring::Entry::new(KEYRING_SERVICE, name)
+            .and_then(|entry| entry.set_password(secret))
+        {
+            Ok(()) => return Ok(format!("{}{}", KEYRING_PREFIX, name)),
+            Err(e) => status!(
+                "The keyring is not available ({}), storing the {} in the secrets file instead",
+                e,
+                name
+            ),
+        }
+    }
+
+    let path = get_secrets_path()?;
+    let mut secrets = read_secrets(&path)?;
+    secrets.insert(name.to_string(), secret.to_string());
+    write_secrets(&path, &secrets)?;
+    Ok(format!("{}{}", FILE_PREFIX, name))
+}
+
+fn get_secrets_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    Ok(config::get_config_dir()?.join("secrets.toml"))
+}
+
+fn read_secrets(path: &Path) -> Result<BTreeMap<String, String>, Box<dyn std::error::Error>> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(toml::from_str(&contents)?),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write_secrets(
+    path: &Path,
+    secrets: &BTreeMap<String, String>,
+) -> Result<(), Box<dyn std::error::Error>> {
+    write_private(path, toml::to_string(secrets)?.as_bytes())?;
+    Ok(())
+}
+
+/// Writes `contents` to a file only the current user can read, such as the secrets file or
+/// a cached access token.
+///
+/// The file is created readable by the owner only, and an existing file is made so, before
+/// anything is written, so the contents are never readable by others, not even briefly.
+pub fn write_private(path: &Path, contents: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    let mut file = options.open(path)?;
+    #[cfg(unix)]
+    {
+        // the mode only applies to new files, a file written by an older version keeps its own
+        use std::os::unix::fs::PermissionsExt;
+        file.set_permissions(fs::Permissions::from_mode(0o600))?;
+    }
+    file.write_all(contents)
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_resolve_references() {
+        env::set_var("PDF_TRANSLATOR_TEST_SECRET", "from-env");
+        assert_eq!(
+            resolve("unused_secret", "env:PDF_TRANSLATOR_TEST_SECRET"),
+            "from-env"
+        );
+        // plaintext values saved by older versions still work
+        assert_eq!(resolve("unused_secret", "plain"), "plain");
+
+        // the dedicated variable wins over the config file
+        env::set_var(env_var("test_secret"), "override");
+        assert_eq!(resolve("test_secret", "plain"), "override");
+        assert_eq!(env_var("api_key"), "PDF_TRANSLATOR_API_KEY");
+    }
+
+    #[test]
+    fn test_mask() {
+        assert_eq!(mask("AIzaSyD-example-key-1234"), "********1234");
+        assert_eq!(mask("short"), "********");
+        assert_eq!(mask("keyring:default.api_key"), "keyring:default.api_key");
+        assert_eq!(mask(""), "");
+    }
+
+    #[test]
+    fn test_secrets_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("secrets.toml");
+        // a file left readable by others is made private before the secrets are written
+        fs::write(&path, "").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&path, fs::Permissions::from_mode(0o644)).unwrap();
+        }
+        let mut secrets = BTreeMap::new();
+        secrets.insert("api_key".to_string(), "secret".to_string());
+        write_secrets(&path, &secrets).unwrap();
+
+        assert_eq!(read_secrets(&path).unwrap(), secrets);
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
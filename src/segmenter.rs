@@ -0,0 +1,44 @@
+//! Splits the extracted text into the segments that are translated one at a time.
+//!
+//! Every line of the `pdftotext -layout` output is a segment, so the translation keeps the
+//! layout of the page line for line.
+
+/// A segment and its position in the document, the index of the line in the extracted text.
+pub type Segment = (usize, String);
+
+/// Splits `text` into one segment per line.
+pub fn split_lines(text: &str) -> Vec<Segment> {
+    text.lines()
+        .enumerate()
+        .map(|(idx, line)| (idx, line.to_string()))
+        .collect()
+}
+
+/// Whether a segment has any text to translate, blank lines are kept as they are.
+pub fn needs_translation(text: &str) -> bool {
+    !text.trim().is_empty()
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_split_lines() {
+        let segments = split_lines("Hello World!\n\n    Indented\n\u{c}");
+
+        assert_eq!(
+            segments,
+            vec![
+                (0, "Hello World!".to_string()),
+                (1, "".to_string()),
+                (2, "    Indented".to_string()),
+                (3, "\u{c}".to_string()),
+            ]
+        );
+        assert!(needs_translation(&segments[2].1));
+        assert!(!needs_translation(&segments[1].1));
+        // the form feed that ends a page is not text either
+        assert!(!needs_translation(&segments[3].1));
+    }
+}
@@ -0,0 +1,803 @@
+//! Translates the extracted text line by line with the configured translation backend,
+//! and detects languages and lists the languages the backend supports.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+
+use crate::auth;
+use crate::cache;
+use crate::config;
+use crate::error::{self, Error};
+use crate::job;
+use crate::segmenter::{self, Segment};
+use crate::status;
+
+/// The Cloud Translation API, which a profile can replace with a proxy or a mock server.
+pub const GOOGLE_API_ENDPOINT: &str = "https://translation.googleapis.com";
+const GOOGLE_TRANSLATE_API_PATH: &str = "/language/translate/v2";
+const GOOGLE_DETECT_API_PATH: &str = "/language/translate/v2/detect";
+const GOOGLE_LANGUAGES_API_PATH: &str = "/language/translate/v2/languages";
+const GOOGLE_V3_API_PATH: &str = "/v3";
+
+/// The URL of `path` on the API endpoint of the configuration.
+fn api_url(config: &config::Config, path: &str) -> String {
+    format!("{}{}", config.get_endpoint().trim_end_matches('/'), path)
+}
+
+/// The translation API used to translate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize, clap::ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    /// Google Cloud Translation Basic (v2), works with an API key
+    #[default]
+    GoogleV2,
+    /// Google Cloud Translation Advanced (v3), supports glossaries and custom models,
+    /// needs a project ID and OAuth credentials
+    GoogleV3,
+}
+
+impl Backend {
+    /// The name of the backend in the configuration and on the command line.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Backend::GoogleV2 => "google-v2",
+            Backend::GoogleV3 => "google-v3",
+        }
+    }
+}
+
+/// The backend chosen in the configuration.
+pub fn active_backend() -> Backend {
+    config::Config::load_or_default()
+        .map(|config| config.get_backend())
+        .unwrap_or_default()
+}
+
+/// The source language that makes the backend detect the language of every line.
+pub const AUTO_DETECT: &str = "auto";
+
+#[derive(Serialize)]
+struct TranslateRequest {
+    q: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<String>,
+    target: String,
+    format: String,
+    key: String,
+}
+
+#[derive(Serialize)]
+struct DetectRequest {
+    q: String,
+    key: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct V3TranslateRequest {
+    contents: Vec<String>,
+    mime_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source_language_code: Option<String>,
+    target_language_code: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    glossary_config: Option<V3GlossaryConfig>,
+}
+
+#[derive(Serialize)]
+struct V3GlossaryConfig {
+    glossary: String,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct V3DetectRequest {
+    content: String,
+    mime_type: String,
+}
+
+/// The `projects/{project_id}/locations/{location}` parent of every v3 request.
+fn v3_parent(backend: &GoogleBackend) -> error::Result<String> {
+    if backend.project_id.is_empty() {
+        return Err(Error::Config("The google-v3 backend needs a project ID, set it with 'config set project_id <PROJECT_ID>'".to_string()));
+    }
+    Ok(format!(
+        "projects/{}/locations/{}",
+        backend.project_id,
+        backend.config.get_location()
+    ))
+}
+
+/// Expands a model or glossary ID to its full resource name, leaving full names as they are.
+fn v3_resource(parent: &str, kind: &str, id: &str) -> String {
+    if id.starts_with("projects/") {
+        id.to_string()
+    } else {
+        format!("{}/{}/{}", parent, kind, id)
+    }
+}
+
+/// What the translations are cached under, so switching backend, model or glossary does
+/// not reuse translations made with another one.
+fn cache_scope(config: &config::Config) -> String {
+    match config.get_backend() {
+        Backend::GoogleV2 => Backend::GoogleV2.name().to_string(),
+        Backend::GoogleV3 => format!(
+            "{}:{}:{}:{}",
+            Backend::GoogleV3.name(),
+            config.get_location(),
+            config.get_model(),
+            config.get_glossary()
+        ),
+    }
+}
+
+/// Translates a single line, returning the translation and the detected source language
+/// when `source` is `None`.
+async fn translate_line(
+    backend: &GoogleBackend,
+    line: &str,
+    source: Option<&str>,
+    target: &str,
+) -> error::Result<(String, Option<String>)> {
+    let config = &backend.config;
+    match config.get_backend() {
+        Backend::GoogleV2 => {
+            let payload = TranslateRequest {
+                q: line.to_string(),
+                source: source.map(str::to_string),
+                target: target.to_string(),
+                format: "text".to_string(),
+                key: backend.api_key.clone(),
+            };
+            let response = post(
+                backend,
+                &api_url(config, GOOGLE_TRANSLATE_API_PATH),
+                &payload,
+            )
+            .await?;
+            parse_response(&response)
+        }
+        Backend::GoogleV3 => {
+            let parent = v3_parent(backend)?;
+            let model = config.get_model();
+            let glossary = config.get_glossary();
+            let payload = V3TranslateRequest {
+                contents: vec![line.to_string()],
+                mime_type: "text/plain".to_string(),
+                source_language_code: source.map(str::to_string),
+                target_language_code: target.to_string(),
+                model: (!model.is_empty()).then(|| v3_resource(&parent, "models", &model)),
+                glossary_config: (!glossary.is_empty()).then(|| V3GlossaryConfig {
+                    glossary: v3_resource(&parent, "glossaries", &glossary),
+                }),
+            };
+            let endpoint = format!(
+                "{}/{}:translateText",
+                api_url(config, GOOGLE_V3_API_PATH),
+                parent
+            );
+            let response = post(backend, &endpoint, &payload).await?;
+            parse_v3_response(&response)
+        }
+    }
+}
+
+/// A document to translate into one language.
+#[derive(Debug, Clone)]
+pub struct TranslateInput {
+    /// The segments of the document
+    pub formatted_content: Vec<Segment>,
+    /// The source language, or [`AUTO_DETECT`] to detect it for every line
+    pub source: String,
+    /// The language to translate into
+    pub target: String,
+    /// Records the translated segments so an interrupted translation can be resumed
+    pub job: job::JobManifest,
+    /// Translations reused across documents
+    pub cache: cache::SharedCache,
+}
+
+async fn post(
+    backend: &GoogleBackend,
+    endpoint: &str,
+    payload: &impl Serialize,
+) -> error::Result<serde_json::Value> {
+    let mut refreshed = false;
+    loop {
+        let request = backend
+            .client
+            .post(endpoint)
+            .header("x-goog-user-project", &backend.project_id)
+            .header("Content-Type", "application/json; charset=utf-8")
+            .json(payload);
+
+        let response = send(backend, request).await?;
+        // a minted token can be revoked before it expires, mint a new one and try again once
+        if let Ok(credentials) = &backend.credentials {
+            if response.status() == reqwest::StatusCode::UNAUTHORIZED
+                && credentials.can_refresh()
+                && !refreshed
+            {
+                credentials.invalidate();
+                refreshed = true;
+                continue;
+            }
+        }
+
+        return read_response(&backend.config, response).await;
+    }
+}
+
+/// Adds the access token to `request` and sends it.
+async fn send(
+    google: &GoogleBackend,
+    mut request: reqwest::RequestBuilder,
+) -> error::Result<reqwest::Response> {
+    let backend = google.config.get_backend().name();
+    let credentials = google
+        .credentials
+        .as_ref()
+        .map_err(|e| Error::auth(backend, e.clone()))?;
+    let token = credentials.access_token().await.map_err(|e| {
+        // failing to reach the token endpoint is a network problem, not a credential one
+        match e.downcast_ref::<reqwest::Error>() {
+            Some(e) => Error::network(backend, e),
+            None => Error::auth(backend, e.to_string()),
+        }
+    })?;
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    request.send().await.map_err(|e| Error::network(backend, e))
+}
+
+/// Reads the JSON body of a response, telling rejected credentials apart from other errors.
+async fn read_response(
+    config: &config::Config,
+    response: reqwest::Response,
+) -> error::Result<serde_json::Value> {
+    let backend = config.get_backend().name();
+    let status = response.status();
+    let body = response.json::<serde_json::Value>().await;
+    if status.is_success() {
+        return body.map_err(|e| Error::backend(backend, format!("Unexpected response: {}", e)));
+    }
+
+    let body = body.unwrap_or_default();
+    let message =
+        api_error(&body).unwrap_or_else(|| format!("Google Translate API error {}", status));
+    // an invalid API key is answered with 400 Bad Request rather than 401 Unauthorized
+    let invalid_key = body["error"]["details"].as_array().is_some_and(|details| {
+        details
+            .iter()
+            .any(|detail| detail["reason"] == "API_KEY_INVALID")
+    });
+    if status == reqwest::StatusCode::UNAUTHORIZED
+        || status == reqwest::StatusCode::FORBIDDEN
+        || invalid_key
+    {
+        Err(Error::auth(backend, message))
+    } else {
+        Err(Error::backend(backend, message))
+    }
+}
+
+/// The message of an error response from the API, if `v` is one.
+fn api_error(v: &serde_json::Value) -> Option<String> {
+    if v["error"].is_null() {
+        return None;
+    }
+    Some(format!(
+        "Google Translate API error {}: {}",
+        v["error"]["code"],
+        v["error"]["message"].as_str().unwrap_or("unknown error")
+    ))
+}
+
+/// A translation service that the segments of a document are sent to.
+///
+/// [`GoogleBackend`] is the one set up with the configuration, other services can be used
+/// by implementing this trait and passing them to [`translate_with`].
+#[async_trait::async_trait]
+pub trait TranslationBackend: Send + Sync {
+    /// Identifies the backend and the settings that change its translations, such as a
+    /// glossary. Translations are cached under it.
+    fn cache_scope(&self) -> String;
+
+    /// Translates `text` into `target`, returning the translation and, when `source` is
+    /// `None`, the language that was detected.
+    async fn translate(
+        &self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> error::Result<(String, Option<String>)>;
+
+    /// Detects the language of `text`, returning the language code and the confidence.
+    async fn detect(&self, text: &str) -> error::Result<(String, f64)>;
+
+    /// The languages the backend supports as `(code, name)` pairs.
+    async fn languages(&self) -> error::Result<Vec<(String, String)>>;
+}
+
+/// Google Cloud Translation, using the backend, credentials and settings of a profile.
+pub struct GoogleBackend {
+    config: config::Config,
+    client: reqwest::Client,
+    /// The API key, read once from the keyring or secrets file rather than for every request
+    api_key: String,
+    /// The credentials the access tokens are minted from, discovered once like the API key.
+    /// A discovery error is reported by the first request rather than by [`GoogleBackend::new`].
+    credentials: Result<auth::Credentials, String>,
+    /// The project to bill requests to, see [`auth::project_id`]
+    project_id: String,
+}
+
+impl GoogleBackend {
+    /// The backend of the profile in use in `config`.
+    pub fn new(config: config::Config) -> GoogleBackend {
+        let credentials = auth::Credentials::discover(&config).map_err(|e| e.to_string());
+        let project_id = auth::project_id_with(&config, || credentials.as_ref().ok().cloned());
+        GoogleBackend {
+            api_key: config.get_api_key(),
+            credentials,
+            project_id,
+            config,
+            client: reqwest::Client::new(),
+        }
+    }
+
+    /// The backend of the profile in use.
+    pub fn from_config() -> error::Result<GoogleBackend> {
+        Ok(GoogleBackend::new(config::Config::load_or_default()?))
+    }
+}
+
+#[async_trait::async_trait]
+impl TranslationBackend for GoogleBackend {
+    fn cache_scope(&self) -> String {
+        cache_scope(&self.config)
+    }
+
+    async fn translate(
+        &self,
+        text: &str,
+        source: Option<&str>,
+        target: &str,
+    ) -> error::Result<(String, Option<String>)> {
+        translate_line(self, text, source, target).await
+    }
+
+    async fn detect(&self, text: &str) -> error::Result<(String, f64)> {
+        let response = match self.config.get_backend() {
+            Backend::GoogleV2 => {
+                let payload = DetectRequest {
+                    q: text.to_string(),
+                    key: self.api_key.clone(),
+                };
+                post(
+                    self,
+                    &api_url(&self.config, GOOGLE_DETECT_API_PATH),
+                    &payload,
+                )
+                .await?
+            }
+            Backend::GoogleV3 => {
+                let payload = V3DetectRequest {
+                    content: text.to_string(),
+                    mime_type: "text/plain".to_string(),
+                };
+                let endpoint = format!(
+                    "{}/{}:detectLanguage",
+                    api_url(&self.config, GOOGLE_V3_API_PATH),
+                    v3_parent(self)?
+                );
+                post(self, &endpoint, &payload).await?
+            }
+        };
+        parse_detect_response(&response).ok_or_else(|| {
+            Error::backend(
+                self.config.get_backend().name(),
+                "Could not detect the source language",
+            )
+        })
+    }
+
+    async fn languages(&self) -> error::Result<Vec<(String, String)>> {
+        let request = match self.config.get_backend() {
+            Backend::GoogleV2 => self
+                .client
+                .get(api_url(&self.config, GOOGLE_LANGUAGES_API_PATH))
+                .query(&[("target", "en")])
+                .header("x-goog-api-key", &self.api_key),
+            Backend::GoogleV3 => self
+                .client
+                .get(format!(
+                    "{}/{}/supportedLanguages",
+                    api_url(&self.config, GOOGLE_V3_API_PATH),
+                    v3_parent(self)?
+                ))
+                .query(&[("displayLanguageCode", "en")]),
+        }
+        .header("x-goog-user-project", &self.project_id);
+        let response = send(self, request).await?;
+        let response = read_response(&self.config, response).await?;
+
+        parse_languages_response(&response).ok_or_else(|| {
+            Error::backend(
+                self.config.get_backend().name(),
+                "Unexpected response when fetching the supported languages",
+            )
+        })
+    }
+}
+
+/// Translates every line of the input with the backend of the configuration, see
+/// [`translate_with`].
+pub async fn translate_text(input: TranslateInput) -> error::Result<Vec<Segment>> {
+    translate_with(&GoogleBackend::from_config()?, input).await
+}
+
+/// Translates every line of the input, recording each finished line in the job manifest
+/// and reusing lines that an earlier, interrupted run already translated or that are in
+/// the cache.
+pub async fn translate_with(
+    backend: &dyn TranslationBackend,
+    mut input: TranslateInput,
+) -> error::Result<Vec<Segment>> {
+    let scope = backend.cache_scope();
+    let mut translated_texts = Vec::new();
+    let mut detected_languages: BTreeMap<String, usize> = BTreeMap::new();
+
+    let source = if input.source == AUTO_DETECT {
+        None
+    } else {
+        Some(input.source.clone())
+    };
+
+    for (line_number, line) in input.formatted_content {
+        if let Some(translated_line) = input.job.get(line_number) {
+            translated_texts.push((line_number, translated_line.clone()));
+            continue;
+        }
+
+        // there is nothing to translate or detect in blank lines
+        if !segmenter::needs_translation(&line) {
+            translated_texts.push((line_number, line));
+            continue;
+        }
+
+        let cached = input
+            .cache
+            .lock()
+            .map_err(|_| "Translation cache is poisoned")?
+            .get(&scope, &input.source, &input.target, &line);
+        if let Some(translated_line) = cached {
+            input.job.complete(line_number, &translated_line)?;
+            translated_texts.push((line_number, translated_line));
+            continue;
+        }
+
+        let (translated_line, detected_language) = backend
+            .translate(&line, source.as_deref(), &input.target)
+            .await
+            .map_err(|e| e.at_segment(line_number))?;
+        if let Some(language) = detected_language {
+            *detected_languages.entry(language).or_default() += 1;
+        }
+        input
+            .cache
+            .lock()
+            .map_err(|_| "Translation cache is poisoned")?
+            .insert(
+                &scope,
+                &input.source,
+                &input.target,
+                &line,
+                &translated_line,
+            );
+        input.job.complete(line_number, &translated_line)?;
+        translated_texts.push((line_number, translated_line));
+    }
+
+    if !detected_languages.is_empty() {
+        let summary: Vec<String> = detected_languages
+            .iter()
+            .map(|(language, lines)| format!("{} ({} lines)", language, lines))
+            .collect();
+        status!(
+            "Detected source languages for '{}': {}",
+            input.target,
+            summary.join(", ")
+        );
+    }
+
+    input.job.finish()?;
+    Ok(translated_texts)
+}
+
+/// Translates a single text that is not a segment of the document, such as a title or a
+/// tooltip, reusing and filling `cache` like [`translate_with`].
+///
+/// `source` can be [`AUTO_DETECT`] to let the backend detect the language of the text.
+pub async fn translate_cached(
+    backend: &dyn TranslationBackend,
+    text: &str,
+    source: &str,
+    target: &str,
+    cache: &cache::SharedCache,
+) -> error::Result<String> {
+    if !segmenter::needs_translation(text) {
+        return Ok(text.to_string());
+    }
+
+    let scope = backend.cache_scope();
+    let cached = cache
+        .lock()
+        .map_err(|_| "Translation cache is poisoned")?
+        .get(&scope, source, target, text);
+    if let Some(translation) = cached {
+        return Ok(translation);
+    }
+
+    let source_code = (source != AUTO_DETECT).then_some(source);
+    let (translation, _) = backend.translate(text, source_code, target).await?;
+    cache
+        .lock()
+        .map_err(|_| "Translation cache is poisoned")?
+        .insert(&scope, source, target, text, &translation);
+    Ok(translation)
+}
+
+/// Detects the language of `text` with the backend of the configuration, returning the
+/// language code and the confidence.
+pub async fn detect_language(text: &str) -> error::Result<(String, f64)> {
+    GoogleBackend::from_config()?.detect(text).await
+}
+
+/// Fetches the languages the backend of the configuration supports as `(code, name)` pairs.
+pub async fn fetch_languages() -> error::Result<Vec<(String, String)>> {
+    GoogleBackend::from_config()?.languages().await
+}
+
+/// Reads the languages from a v2 (`data.languages[].language`) or a v3
+/// (`languages[].languageCode`) response.
+fn parse_languages_response(v: &serde_json::Value) -> Option<Vec<(String, String)>> {
+    let (languages, code_key, name_key) = match v["data"]["languages"].as_array() {
+        Some(languages) => (languages, "language", "name"),
+        None => (v["languages"].as_array()?, "languageCode", "displayName"),
+    };
+    languages
+        .iter()
+        .map(|language| {
+            let code = language[code_key].as_str()?.to_string();
+            let name = language[name_key].as_str().unwrap_or(&code).to_string();
+            Some((code, name))
+        })
+        .collect()
+}
+
+/// Returns the translated text and, when the source language was left out of the request,
+/// the language the backend detected.
+fn parse_response(v: &serde_json::Value) -> error::Result<(String, Option<String>)> {
+    if let Some(e) = api_error(v) {
+        return Err(Error::backend(Backend::GoogleV2.name(), e));
+    }
+    let translation = &v["data"]["translations"][0];
+    let translated_text = translation["translatedText"]
+        .as_str()
+        .ok_or_else(|| {
+            Error::backend(
+                Backend::GoogleV2.name(),
+                "Unexpected response from the Google Translate API",
+            )
+        })?
+        .to_string();
+    let detected_language = translation["detectedSourceLanguage"]
+        .as_str()
+        .map(str::to_string);
+    Ok((translated_text, detected_language))
+}
+
+/// Returns the translated text of a v3 response, preferring the glossary translation when
+/// a glossary was used, and the detected language when no source language was given.
+fn parse_v3_response(v: &serde_json::Value) -> error::Result<(String, Option<String>)> {
+    if let Some(e) = api_error(v) {
+        return Err(Error::backend(Backend::GoogleV3.name(), e));
+    }
+    let translation = if v["glossaryTranslations"][0].is_object() {
+        &v["glossaryTranslations"][0]
+    } else {
+        &v["translations"][0]
+    };
+    let translated_text = translation["translatedText"]
+        .as_str()
+        .ok_or_else(|| {
+            Error::backend(
+                Backend::GoogleV3.name(),
+                "Unexpected response from the Google Translate API",
+            )
+        })?
+        .to_string();
+    let detected_language = translation["detectedLanguageCode"]
+        .as_str()
+        .map(str::to_string);
+    Ok((translated_text, detected_language))
+}
+
+/// Reads the most likely language from a v2 or v3 detection response.
+fn parse_detect_response(v: &serde_json::Value) -> Option<(String, f64)> {
+    let v2 = &v["data"]["detections"][0][0];
+    let (language, confidence) = if v2.is_object() {
+        (v2["language"].as_str()?, &v2["confidence"])
+    } else {
+        let v3 = &v["languages"][0];
+        (v3["languageCode"].as_str()?, &v3["confidence"])
+    };
+    Some((
+        language.to_string(),
+        confidence.as_f64().unwrap_or_default(),
+    ))
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_parse_detected_language() {
+        let response = serde_json::json!({
+            "data": {"translations": [{"translatedText": "Hej", "detectedSourceLanguage": "de"}]}
+        });
+        let (text, language) = parse_response(&response).unwrap();
+        assert_eq!(text, "Hej");
+        assert_eq!(language.as_deref(), Some("de"));
+
+        let response = serde_json::json!({
+            "data": {"detections": [[{"language": "fi", "confidence": 0.98, "isReliable": false}]]}
+        });
+        assert_eq!(
+            parse_detect_response(&response),
+            Some(("fi".to_string(), 0.98))
+        );
+        assert_eq!(parse_detect_response(&serde_json::json!({})), None);
+    }
+
+    #[test]
+    fn test_parse_languages() {
+        let response = serde_json::json!({
+            "data": {"languages": [
+                {"language": "sv", "name": "Swedish"},
+                {"language": "zh-TW", "name": "Chinese (Traditional)"}
+            ]}
+        });
+        assert_eq!(
+            parse_languages_response(&response),
+            Some(vec![
+                ("sv".to_string(), "Swedish".to_string()),
+                ("zh-TW".to_string(), "Chinese (Traditional)".to_string())
+            ])
+        );
+
+        let error = serde_json::json!({"error": {"code": 403, "message": "denied"}});
+        assert_eq!(parse_languages_response(&error), None);
+    }
+
+    #[test]
+    fn test_parse_v3_responses() {
+        let response = serde_json::json!({
+            "translations": [{"translatedText": "Hej", "detectedLanguageCode": "en"}],
+            "glossaryTranslations": [{"translatedText": "Tjena"}]
+        });
+        assert_eq!(
+            parse_v3_response(&response).unwrap(),
+            ("Tjena".to_string(), None)
+        );
+
+        let response =
+            serde_json::json!({"languages": [{"languageCode": "de", "confidence": 0.9}]});
+        assert_eq!(
+            parse_detect_response(&response),
+            Some(("de".to_string(), 0.9))
+        );
+
+        let response = serde_json::json!({
+            "languages": [{"languageCode": "sv", "displayName": "Swedish", "supportSource": true}]
+        });
+        assert_eq!(
+            parse_languages_response(&response),
+            Some(vec![("sv".to_string(), "Swedish".to_string())])
+        );
+    }
+
+    #[test]
+    fn test_v3_resource_names() {
+        let parent = "projects/p/locations/global";
+        assert_eq!(
+            v3_resource(parent, "models", "general/nmt"),
+            "projects/p/locations/global/models/general/nmt"
+        );
+        assert_eq!(
+            v3_resource(
+                parent,
+                "glossaries",
+                "projects/q/locations/us-central1/glossaries/g"
+            ),
+            "projects/q/locations/us-central1/glossaries/g"
+        );
+    }
+
+    #[test]
+    fn test_api_errors_are_not_silent() {
+        let error = serde_json::json!({
+            "error": {"code": 401, "message": "Request had invalid authentication credentials."}
+        });
+        assert_eq!(
+            parse_response(&error).unwrap_err().to_string(),
+            "google-v2 failed: Google Translate API error 401: Request had invalid authentication credentials."
+        );
+    }
+
+    /// Translates by upper-casing, counting the segments sent to it.
+    #[allow(dead_code)]
+    struct UppercaseBackend {
+        calls: std::sync::atomic::AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl TranslationBackend for UppercaseBackend {
+        fn cache_scope(&self) -> String {
+            "uppercase".to_string()
+        }
+
+        async fn translate(
+            &self,
+            text: &str,
+            _source: Option<&str>,
+            _target: &str,
+        ) -> error::Result<(String, Option<String>)> {
+            self.calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok((text.to_uppercase(), None))
+        }
+
+        async fn detect(&self, _text: &str) -> error::Result<(String, f64)> {
+            Ok(("en".to_string(), 1.0))
+        }
+
+        async fn languages(&self) -> error::Result<Vec<(String, String)>> {
+            Ok(vec![("sv".to_string(), "Swedish".to_string())])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_translate_with_custom_backend() {
+        let backend = UppercaseBackend {
+            calls: Default::default(),
+        };
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("job.jsonl");
+        let input = TranslateInput {
+            formatted_content: crate::segmenter::split_lines("Hello\n\nHello\nWorld"),
+            source: "en".to_string(),
+            target: "sv".to_string(),
+            job: job::JobManifest::open_at(path, "abc", "en", "sv", false).unwrap(),
+            cache: cache::TranslationCache::default().shared(),
+        };
+
+        let translated = translate_with(&backend, input).await.unwrap();
+        assert_eq!(
+            translated,
+            vec![
+                (0, "HELLO".to_string()),
+                (1, "".to_string()),
+                (2, "HELLO".to_string()),
+                (3, "WORLD".to_string()),
+            ]
+        );
+        // the blank line is not sent and the repeated line comes from the cache
+        assert_eq!(backend.calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,172 @@
+//! A minimal reader for the XML and XHTML that poppler's tools write.
+//!
+//! The markup is read with quick-xml, which handles comments, CDATA and `>` in attribute
+//! values, and flattened into a list of tags and text. Entities are resolved here instead,
+//! so that an unknown one such as an HTML `&nbsp;` is kept as it is rather than failing.
+
+use quick_xml::events::{BytesStart, Event};
+use std::collections::BTreeMap;
+
+/// A piece of the XML: a start tag with its attributes, an end tag or text.
+#[derive(Debug, PartialEq)]
+pub enum Token {
+    Start(String, BTreeMap<String, String>),
+    End(String),
+    Text(String),
+}
+
+/// Replaces the entities XML escapes text with by the characters they stand for.
+fn unescape(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        result.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let character = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match character {
+            Some(character) => {
+                result.push(character);
+                rest = &rest[end + 1..];
+            }
+            // not an entity, keep the ampersand as it is
+            None => {
+                result.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    result.push_str(rest);
+    result
+}
+
+/// The name and the `name="value"` pairs of a tag.
+fn parse_start(tag: &BytesStart) -> (String, BTreeMap<String, String>) {
+    let name = String::from_utf8_lossy(tag.name().as_ref()).to_string();
+    let attributes = tag
+        .attributes()
+        .with_checks(false)
+        .filter_map(Result::ok)
+        .map(|attribute| {
+            (
+                String::from_utf8_lossy(attribute.key.as_ref()).to_string(),
+                unescape(&String::from_utf8_lossy(&attribute.value)),
+            )
+        })
+        .collect();
+    (name, attributes)
+}
+
+/// Splits the XML into tags and text, skipping the declaration, doctype, comments and
+/// processing instructions. A self-closing tag gives a start and an end token, and the
+/// contents of a CDATA section are text.
+///
+/// The end tags do not have to match the start tags. XML that is cut off or malformed gives
+/// the tokens up to where it can no longer be read.
+pub fn tokenize(xml: &str) -> Vec<Token> {
+    let mut reader = quick_xml::Reader::from_str(xml);
+    reader.expand_empty_elements(true).check_end_names(false);
+    let mut tokens = Vec::new();
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(tag)) => {
+                let (name, attributes) = parse_start(&tag);
+                tokens.push(Token::Start(name, attributes));
+            }
+            Ok(Event::End(tag)) => tokens.push(Token::End(
+                String::from_utf8_lossy(tag.name().as_ref()).to_string(),
+            )),
+            Ok(Event::Text(text)) => tokens.push(Token::Text(unescape(&String::from_utf8_lossy(
+                &text.into_inner(),
+            )))),
+            Ok(Event::CData(text)) => tokens.push(Token::Text(
+                String::from_utf8_lossy(&text.into_inner()).to_string(),
+            )),
+            Ok(Event::Eof) | Err(_) => break,
+            Ok(_) => {}
+        }
+    }
+    tokens
+}
+
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[test]
+    fn test_unescape() {
+        assert_eq!(
+            unescape("a &lt;b&gt; &#x41;&#66; &unknown; &"),
+            "a <b> AB &unknown; &"
+        );
+    }
+
+    #[test]
+    fn test_tokenize() {
+        assert_eq!(
+            tokenize("<?xml version=\"1.0\"?><item page='2'>A &amp; B</item><br/>"),
+            vec![
+                Token::Start(
+                    "item".to_string(),
+                    BTreeMap::from([("page".to_string(), "2".to_string())])
+                ),
+                Token::Text("A & B".to_string()),
+                Token::End("item".to_string()),
+                Token::Start("br".to_string(), BTreeMap::new()),
+                Token::End("br".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_markup_with_angle_brackets() {
+        let xml = "<!DOCTYPE pdf2xml SYSTEM \"pdf2xml.dtd\">\
+                   <!-- <item>not an item</item> -->\
+                   <a href=\"x>y\" title='1 > 0'>A<![CDATA[ <b> & ]]>B</a>";
+        assert_eq!(
+            tokenize(xml),
+            vec![
+                Token::Start(
+                    "a".to_string(),
+                    BTreeMap::from([
+                        ("href".to_string(), "x>y".to_string()),
+                        ("title".to_string(), "1 > 0".to_string()),
+                    ])
+                ),
+                Token::Text("A".to_string()),
+                Token::Text(" <b> & ".to_string()),
+                Token::Text("B".to_string()),
+                Token::End("a".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_malformed() {
+        // poppler can close formatting tags in another order than it opened them
+        assert_eq!(
+            tokenize("<i><b>x</i></b><item page=\"3"),
+            vec![
+                Token::Start("i".to_string(), BTreeMap::new()),
+                Token::Start("b".to_string(), BTreeMap::new()),
+                Token::Text("x".to_string()),
+                Token::End("i".to_string()),
+                Token::End("b".to_string()),
+            ]
+        );
+    }
+}